@@ -1,6 +1,6 @@
 use gcp_rust_tools::{LogEntry, MetricData, ObservabilityClient, TraceSpan};
 use std::collections::HashMap;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -29,47 +29,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     labels.insert("service".to_string(), "example-service".to_string());
 
     client.send_metric(
-        MetricData::new(
-            "custom.googleapis.com/example/requests_total",
-            42.0,
-            "INT64",
-            "GAUGE",
-        )
-        .with_labels(labels),
+        MetricData::int64("custom.googleapis.com/example/requests_total", 42).with_labels(labels),
     )?;
 
-    client.send_metric(MetricData::new(
+    client.send_metric(MetricData::double(
         "custom.googleapis.com/example/response_time_ms",
         125.5,
-        "DOUBLE",
-        "GAUGE",
     ))?;
 
     // Example 4: Distributed tracing using struct
-    let trace_id = ObservabilityClient::generate_trace_id();
-    let span_id = ObservabilityClient::generate_span_id();
-    let child_span_id = ObservabilityClient::generate_span_id();
+    // Parent span: trace and span IDs are generated, timed from now
+    let parent = TraceSpan::started_at("HTTP Request", Instant::now());
 
-    // Parent span
-    client.send_trace(TraceSpan::new(
-        trace_id.clone(),
-        span_id.clone(),
-        "HTTP Request",
-        SystemTime::now(),
-        Duration::from_millis(150),
-    ))?;
+    // Child span: same trace, parented to the request span
+    let child = parent.child_started_at("Database Query", Instant::now());
+    tokio::time::sleep(Duration::from_millis(20)).await; // the "query"
 
-    // Child span
-    client.send_trace(
-        TraceSpan::new(
-            trace_id.clone(),
-            child_span_id,
-            "Database Query",
-            SystemTime::now(),
-            Duration::from_millis(50),
-        )
-        .with_parent_span_id(span_id),
-    )?;
+    // `finish` fills in the real elapsed durations
+    client.send_trace(child.finish())?;
+    client.send_trace(parent.finish())?;
 
     println!("✅ All observability examples queued!");
     println!("📊 Check your Google Cloud Console:");
@@ -77,8 +55,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   - Metrics: https://console.cloud.google.com/monitoring");
     println!("   - Traces: https://console.cloud.google.com/traces");
 
-    // Give the background worker time to process
-    tokio::time::sleep(Duration::from_secs(2)).await;
+    // Drain the queue and stop the background worker
+    client.shutdown().await?;
 
     Ok(())
 }
@@ -1,8 +1,8 @@
-use gcp_rust_tools::{pubsub::create_pubsub_client, LogEntry, ObservabilityClient};
+use gcp_rust_tools::{pubsub::create_pubsub_client, GcpError, LogEntry, ObservabilityClient};
 use std::sync::Arc;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<(), GcpError> {
     // No credentials/project logic in main.
     // The crate resolves:
     // - credentials from GOOGLE_APPLICATION_CREDENTIALS (or GOOGLE_CREDENTIALS)
@@ -17,16 +17,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let pubsub = create_pubsub_client(None, "dev", topics, subs).await?;
 
     // Fire-and-forget queueing into the background worker.
-    // If the channel is closed, we just continue in this example.
-    let _ = observability.send_log(LogEntry::new("INFO", "Example started"));
+    observability.send_log(LogEntry::new("INFO", "Example started"))?;
+
+    // Topic/subscription checks report `PubSubError`, which also converts into `GcpError`.
+    if !pubsub.topic_exists("events").await? {
+        observability.send_log(LogEntry::new("WARNING", "Topic 'events' is missing"))?;
+    }
 
     // Publish a simple message (fire-and-forget)
     pubsub
         .publish_fire_and_forget("events", serde_json::json!({"hello": "world"}), None)
         .await;
 
-    // Give background work a moment (optional for examples)
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    // Drain the queue and stop the background worker
+    observability.shutdown().await?;
 
     Ok(())
 }
@@ -0,0 +1,430 @@
+//! Where the client's OAuth access tokens come from.
+//!
+//! The default backend shells out to gcloud (activated with the service-account key
+//! file). Credential files of `"type": "external_account"` (Workload Identity
+//! Federation, e.g. GKE, AWS or Azure workloads) are handled natively instead: the
+//! workload's subject token is exchanged at Google's STS for an access token, which is
+//! cached and refreshed shortly before it expires.
+//!
+//! Applications with their own auth flow can instead supply tokens through a
+//! `TokenProvider`, in which case gcloud is never run.
+//!
+//! Any backend's token can additionally be exchanged for a target service
+//! account's token (`Impersonation`), so the writer identity can differ from the
+//! runtime identity.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::ObservabilityError;
+
+const IAM_CREDENTIALS_ENDPOINT: &str = "https://iamcredentials.googleapis.com";
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// Tokens are refreshed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Where a client's credentials were resolved from, see
+/// `ObservabilityClient::credentials_source`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CredentialsSource {
+    /// Service-account key file activated in gcloud, which issues the tokens.
+    KeyFile(PathBuf),
+    /// `"type": "external_account"` configuration file (Workload Identity Federation).
+    ExternalAccount(PathBuf),
+    /// `ObservabilityClientBuilder::with_token_provider`.
+    TokenProvider,
+    /// `ObservabilityClient::new_noop`, which never authenticates.
+    Noop,
+}
+
+impl std::fmt::Display for CredentialsSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialsSource::KeyFile(_) => write!(f, "service-account key file (gcloud)"),
+            CredentialsSource::ExternalAccount(_) => {
+                write!(f, "external account configuration file")
+            }
+            CredentialsSource::TokenProvider => write!(f, "token provider"),
+            CredentialsSource::Noop => write!(f, "none (dry run)"),
+        }
+    }
+}
+
+/// How the client obtains access tokens
+#[derive(Clone, Default)]
+pub(crate) enum AuthBackend {
+    /// `gcloud auth print-access-token` after activating the key file.
+    #[default]
+    Gcloud,
+    /// Workload Identity Federation via STS token exchange.
+    ExternalAccount(Arc<ExternalAccount>),
+    /// A user-supplied async closure.
+    TokenProvider(TokenProvider),
+}
+
+impl AuthBackend {
+    /// Pick the backend for a credentials file based on its `"type"` field.
+    ///
+    /// Anything that isn't an `external_account` config (including unreadable
+    /// files) keeps the gcloud flow, which reports its own errors.
+    pub(crate) fn detect(credentials_path: &str) -> Result<Self, ObservabilityError> {
+        let Ok(contents) = std::fs::read_to_string(credentials_path) else {
+            return Ok(Self::Gcloud);
+        };
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&contents) else {
+            return Ok(Self::Gcloud);
+        };
+        if value.get("type").and_then(|t| t.as_str()) != Some("external_account") {
+            return Ok(Self::Gcloud);
+        }
+        let config: ExternalAccountConfig = serde_json::from_value(value).map_err(|e| {
+            ObservabilityError::SetupError(format!("Invalid external_account credentials: {}", e))
+        })?;
+        Ok(Self::ExternalAccount(Arc::new(ExternalAccount::new(
+            config,
+        ))))
+    }
+
+    /// Short name for `Debug` output; never includes credential contents.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Self::Gcloud => "gcloud",
+            Self::ExternalAccount(_) => "external_account",
+            Self::TokenProvider(_) => "token_provider",
+        }
+    }
+
+    /// Public description of the backend, `credentials_path` being the file it was
+    /// detected from.
+    pub(crate) fn source(&self, credentials_path: &str) -> CredentialsSource {
+        match self {
+            Self::Gcloud => CredentialsSource::KeyFile(credentials_path.into()),
+            Self::ExternalAccount(_) => CredentialsSource::ExternalAccount(credentials_path.into()),
+            Self::TokenProvider(_) => CredentialsSource::TokenProvider,
+        }
+    }
+
+    /// Whether gcloud has to be installed and activated for this backend.
+    pub(crate) fn uses_gcloud(&self) -> bool {
+        matches!(self, Self::Gcloud)
+    }
+}
+
+type TokenFuture =
+    Pin<Box<dyn Future<Output = Result<String, Box<dyn std::error::Error + Send + Sync>>> + Send>>;
+
+/// User-supplied async source of access tokens
+///
+/// Called before every API request, so it should cache tokens itself.
+#[derive(Clone)]
+pub(crate) struct TokenProvider(Arc<dyn Fn() -> TokenFuture + Send + Sync>);
+
+impl TokenProvider {
+    pub(crate) fn new<F, Fut, E>(provider: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Self(Arc::new(move || {
+            let token = provider();
+            Box::pin(async move { token.await.map_err(Into::into) }) as TokenFuture
+        }))
+    }
+
+    pub(crate) async fn access_token(&self) -> Result<String, ObservabilityError> {
+        (self.0)().await.map_err(|e| {
+            ObservabilityError::AuthenticationError(format!("Token provider failed: {}", e))
+        })
+    }
+}
+
+impl std::fmt::Debug for TokenProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TokenProvider")
+    }
+}
+
+/// `"type": "external_account"` credential configuration
+///
+/// Deliberately not `Debug`: `credential_source.headers` may carry secrets.
+#[derive(Deserialize)]
+struct ExternalAccountConfig {
+    audience: String,
+    subject_token_type: String,
+    token_url: String,
+    #[serde(default)]
+    service_account_impersonation_url: Option<String>,
+    credential_source: CredentialSource,
+}
+
+/// Where the workload's own (subject) token is read from
+#[derive(Deserialize)]
+struct CredentialSource {
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    format: Option<CredentialFormat>,
+}
+
+#[derive(Deserialize)]
+struct CredentialFormat {
+    #[serde(rename = "type")]
+    format_type: String,
+    #[serde(default)]
+    subject_token_field_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StsResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImpersonationResponse {
+    access_token: String,
+    expire_time: String,
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// External account credentials with a cached access token
+pub(crate) struct ExternalAccount {
+    config: ExternalAccountConfig,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl ExternalAccount {
+    fn new(config: ExternalAccountConfig) -> Self {
+        Self {
+            config,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// A valid access token, exchanging a fresh subject token when the cached one
+    /// is missing or about to expire.
+    pub(crate) async fn access_token(
+        &self,
+        http: &reqwest::Client,
+    ) -> Result<String, ObservabilityError> {
+        if let Some(cached) = self.cached.lock().unwrap().as_ref() {
+            if cached.expires_at > Instant::now() + EXPIRY_MARGIN {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let subject_token = self.subject_token(http).await?;
+        let (token, lifetime) = self.exchange(http, &subject_token).await?;
+        let (token, lifetime) = match &self.config.service_account_impersonation_url {
+            Some(url) => generate_access_token(http, url, &token, &[]).await?,
+            None => (token, lifetime),
+        };
+
+        *self.cached.lock().unwrap() = Some(CachedToken {
+            token: token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(token)
+    }
+
+    /// Drop the cached token so the next request exchanges a new one.
+    pub(crate) fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+
+    async fn subject_token(&self, http: &reqwest::Client) -> Result<String, ObservabilityError> {
+        let source = &self.config.credential_source;
+        let raw = if let Some(path) = &source.file {
+            tokio::fs::read_to_string(path).await.map_err(|e| {
+                ObservabilityError::AuthenticationError(format!(
+                    "Failed to read subject token from {}: {}",
+                    path, e
+                ))
+            })?
+        } else if let Some(url) = &source.url {
+            let mut request = http.get(url);
+            for (name, value) in &source.headers {
+                request = request.header(name, value);
+            }
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                return Err(ObservabilityError::AuthenticationError(format!(
+                    "Failed to fetch subject token from {}: {}",
+                    url,
+                    response.status()
+                )));
+            }
+            response.text().await?
+        } else {
+            return Err(ObservabilityError::SetupError(
+                "external_account credential_source needs a file or url".to_string(),
+            ));
+        };
+
+        match &source.format {
+            Some(format) if format.format_type == "json" => {
+                let field = format
+                    .subject_token_field_name
+                    .as_deref()
+                    .unwrap_or("access_token");
+                let value: serde_json::Value = serde_json::from_str(&raw)?;
+                value
+                    .get(field)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| {
+                        ObservabilityError::AuthenticationError(format!(
+                            "Subject token response has no '{}' field",
+                            field
+                        ))
+                    })
+            }
+            _ => Ok(raw.trim().to_string()),
+        }
+    }
+
+    async fn exchange(
+        &self,
+        http: &reqwest::Client,
+        subject_token: &str,
+    ) -> Result<(String, Duration), ObservabilityError> {
+        let response = http
+            .post(&self.config.token_url)
+            .form(&[
+                ("grant_type", TOKEN_EXCHANGE_GRANT_TYPE),
+                ("audience", self.config.audience.as_str()),
+                ("scope", CLOUD_PLATFORM_SCOPE),
+                ("requested_token_type", ACCESS_TOKEN_TYPE),
+                (
+                    "subject_token_type",
+                    self.config.subject_token_type.as_str(),
+                ),
+                ("subject_token", subject_token),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ObservabilityError::AuthenticationError(format!(
+                "STS token exchange failed: {} - {}",
+                status, body
+            )));
+        }
+        let sts: StsResponse = response.json().await?;
+        Ok((
+            sts.access_token,
+            Duration::from_secs(sts.expires_in.unwrap_or(3600)),
+        ))
+    }
+}
+
+/// Impersonated target service account, with its cached access token
+pub(crate) struct Impersonation {
+    target: String,
+    delegates: Vec<String>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl Impersonation {
+    pub(crate) fn new(target: String, delegates: Vec<String>) -> Self {
+        Self {
+            target,
+            delegates,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// The target account's token, generated with `base_token` when the cached one
+    /// is missing or about to expire.
+    ///
+    /// The base identity needs `roles/iam.serviceAccountTokenCreator` on the first
+    /// delegate (or on the target when there are no delegates).
+    pub(crate) async fn access_token(
+        &self,
+        http: &reqwest::Client,
+        base_token: &str,
+    ) -> Result<String, ObservabilityError> {
+        if let Some(cached) = self.cached.lock().unwrap().as_ref() {
+            if cached.expires_at > Instant::now() + EXPIRY_MARGIN {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let url = format!(
+            "{}/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+            IAM_CREDENTIALS_ENDPOINT, self.target
+        );
+        let (token, lifetime) =
+            generate_access_token(http, &url, base_token, &self.delegates).await?;
+
+        *self.cached.lock().unwrap() = Some(CachedToken {
+            token: token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(token)
+    }
+
+    /// Drop the cached token so the next request generates a new one.
+    pub(crate) fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
+
+/// Trade a token for a service account's token via IAM Credentials `generateAccessToken`.
+///
+/// `delegates` are service account emails forming the delegation chain, in order.
+async fn generate_access_token(
+    http: &reqwest::Client,
+    url: &str,
+    token: &str,
+    delegates: &[String],
+) -> Result<(String, Duration), ObservabilityError> {
+    let mut body = serde_json::json!({ "scope": [CLOUD_PLATFORM_SCOPE] });
+    if !delegates.is_empty() {
+        body["delegates"] = delegates
+            .iter()
+            .map(|email| format!("projects/-/serviceAccounts/{}", email))
+            .collect();
+    }
+    let response = http.post(url).bearer_auth(token).json(&body).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ObservabilityError::AuthenticationError(format!(
+            "Service account impersonation failed: {} - {}",
+            status, body
+        )));
+    }
+    let body: ImpersonationResponse = response.json().await?;
+    let lifetime = chrono::DateTime::parse_from_rfc3339(&body.expire_time)
+        .ok()
+        .and_then(|expiry| {
+            (expiry.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .ok()
+        })
+        .unwrap_or(Duration::from_secs(3600));
+    Ok((body.access_token, lifetime))
+}
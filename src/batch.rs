@@ -0,0 +1,187 @@
+//! Grouping of queued items into batched requests by the background worker.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::{
+    Handle, LogEntry, MetricData, ObservabilityClient, ObservabilityError, Signal, TraceSpan,
+};
+
+/// How the background worker groups queued items of one signal into requests
+///
+/// A batch is sent once it holds `max_batch_size` items or its oldest item has
+/// waited `max_batch_delay`, whichever comes first. The default (a size of 1)
+/// sends every item on its own as soon as it is dequeued.
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), gcp_rust_tools::ObservabilityError> {
+/// use gcp_rust_tools::{BatchConfig, ObservabilityClient, Signal};
+/// use std::time::Duration;
+///
+/// let client = ObservabilityClient::builder()
+///     .with_batch_config(Signal::Logs, BatchConfig::new(100, Duration::from_millis(500)))
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    pub max_batch_size: usize,
+    pub max_batch_delay: Duration,
+}
+
+impl BatchConfig {
+    pub fn new(max_batch_size: usize, max_batch_delay: Duration) -> Self {
+        Self {
+            max_batch_size: max_batch_size.max(1),
+            max_batch_delay,
+        }
+    }
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+}
+
+/// Queued items of one signal sent together.
+pub(crate) enum QueuedBatch {
+    Logs(Vec<LogEntry>),
+    Metrics(Vec<MetricData>),
+    Traces(Vec<TraceSpan>),
+}
+
+impl QueuedBatch {
+    /// A batch holding `msg` alone, or `msg` back if it isn't a log, metric or span.
+    fn of(msg: Box<dyn Handle>) -> Result<Self, Box<dyn Handle>> {
+        let item: &dyn Any = msg.as_ref();
+        if !(item.is::<LogEntry>() || item.is::<MetricData>() || item.is::<TraceSpan>()) {
+            return Err(msg);
+        }
+        let item: Box<dyn Any> = msg;
+        let item = match item.downcast::<LogEntry>() {
+            Ok(entry) => return Ok(Self::Logs(vec![*entry])),
+            Err(item) => item,
+        };
+        let item = match item.downcast::<MetricData>() {
+            Ok(metric) => return Ok(Self::Metrics(vec![*metric])),
+            Err(item) => item,
+        };
+        let span = item.downcast::<TraceSpan>().expect("checked above");
+        Ok(Self::Traces(vec![*span]))
+    }
+
+    fn signal(&self) -> Signal {
+        match self {
+            Self::Logs(_) => Signal::Logs,
+            Self::Metrics(_) => Signal::Metrics,
+            Self::Traces(_) => Signal::Traces,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Logs(items) => items.len(),
+            Self::Metrics(items) => items.len(),
+            Self::Traces(items) => items.len(),
+        }
+    }
+
+    /// Append `other`, which must be of the same signal.
+    fn append(&mut self, other: Self) {
+        match (self, other) {
+            (Self::Logs(items), Self::Logs(more)) => items.extend(more),
+            (Self::Metrics(items), Self::Metrics(more)) => items.extend(more),
+            (Self::Traces(items), Self::Traces(more)) => items.extend(more),
+            _ => unreachable!("batches are keyed by signal"),
+        }
+    }
+}
+
+#[async_trait]
+impl Handle for QueuedBatch {
+    async fn handle(
+        self: Box<Self>,
+        client: &ObservabilityClient,
+    ) -> Result<(), ObservabilityError> {
+        client.send_queued_batch(*self).await
+    }
+
+    fn signal(&self) -> Option<Signal> {
+        Some(QueuedBatch::signal(self))
+    }
+}
+
+/// Batches the worker is filling, with the time each one is due.
+pub(crate) struct PendingBatches {
+    config: HashMap<Signal, BatchConfig>,
+    pending: HashMap<Signal, (Instant, QueuedBatch)>,
+}
+
+impl PendingBatches {
+    pub(crate) fn new(config: HashMap<Signal, BatchConfig>) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Add `msg` to its signal's batch, returning the batch once it is full.
+    ///
+    /// Items of signals without batching (and other messages) are handed back.
+    pub(crate) fn add(
+        &mut self,
+        msg: Box<dyn Handle>,
+    ) -> Result<Option<QueuedBatch>, Box<dyn Handle>> {
+        let config = msg
+            .signal()
+            .and_then(|signal| self.config.get(&signal).copied())
+            .filter(|config| config.max_batch_size > 1);
+        let Some(config) = config else {
+            return Err(msg);
+        };
+        let batch = QueuedBatch::of(msg)?;
+        let signal = batch.signal();
+        let (due, batch) = match self.pending.remove(&signal) {
+            Some((due, mut pending)) => {
+                pending.append(batch);
+                (due, pending)
+            }
+            None => (Instant::now() + config.max_batch_delay, batch),
+        };
+        if batch.len() >= config.max_batch_size {
+            return Ok(Some(batch));
+        }
+        self.pending.insert(signal, (due, batch));
+        Ok(None)
+    }
+
+    /// When the next batch is due, if any is pending.
+    pub(crate) fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(due, _)| *due).min()
+    }
+
+    /// Remove the batches due by `now`.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<QueuedBatch> {
+        let due: Vec<Signal> = self
+            .pending
+            .iter()
+            .filter(|(_, (due, _))| *due <= now)
+            .map(|(signal, _)| *signal)
+            .collect();
+        due.into_iter()
+            .filter_map(|signal| self.pending.remove(&signal))
+            .map(|(_, batch)| batch)
+            .collect()
+    }
+
+    /// Remove every pending batch, e.g. on shutdown.
+    pub(crate) fn take_all(&mut self) -> Vec<QueuedBatch> {
+        self.pending.drain().map(|(_, (_, batch))| batch).collect()
+    }
+}
@@ -0,0 +1,118 @@
+//! Synchronous facade over `ObservabilityClient` for scripts and non-async binaries.
+//!
+//! `BlockingObservabilityClient` owns a small Tokio runtime and drives every call on
+//! it, so callers don't need `#[tokio::main]`. Like reqwest's blocking client, it must
+//! not be used from inside an async runtime (calls would panic).
+
+use tokio::runtime::Runtime;
+
+use crate::{
+    HealthStatus, LogEntry, MetricData, ObservabilityClient, ObservabilityClientBuilder,
+    ObservabilityError, TraceSpan,
+};
+
+/// Blocking wrapper around `ObservabilityClient`
+///
+/// ```rust,no_run
+/// use gcp_rust_tools::blocking::BlockingObservabilityClient;
+/// use gcp_rust_tools::LogEntry;
+///
+/// # fn main() -> Result<(), gcp_rust_tools::ObservabilityError> {
+/// let client = BlockingObservabilityClient::new(None, Some("my-script".to_string()))?;
+/// client.send_log_blocking(LogEntry::new("INFO", "done"))?;
+/// client.shutdown()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BlockingObservabilityClient {
+    /// `None` once `shutdown` has taken it.
+    inner: Option<ObservabilityClient>,
+    runtime: Runtime,
+}
+
+impl BlockingObservabilityClient {
+    pub fn new(
+        project_id: Option<String>,
+        service_name: Option<String>,
+    ) -> Result<Self, ObservabilityError> {
+        let runtime = build_runtime()?;
+        let inner = runtime.block_on(ObservabilityClient::new(project_id, service_name))?;
+        Ok(Self {
+            inner: Some(inner),
+            runtime,
+        })
+    }
+
+    /// Build the client from a configured `ObservabilityClientBuilder`.
+    pub fn from_builder(builder: ObservabilityClientBuilder) -> Result<Self, ObservabilityError> {
+        let runtime = build_runtime()?;
+        let inner = runtime.block_on(builder.build())?;
+        Ok(Self {
+            inner: Some(inner),
+            runtime,
+        })
+    }
+
+    /// The wrapped async client, e.g. for the fire-and-forget `send_*` methods,
+    /// which don't need a runtime on the caller's side.
+    pub fn client(&self) -> &ObservabilityClient {
+        self.inner
+            .as_ref()
+            .expect("client is only taken by shutdown, which consumes self")
+    }
+
+    /// Send a log and block until the API accepts it.
+    pub fn send_log_blocking(&self, entry: LogEntry) -> Result<(), ObservabilityError> {
+        self.runtime.block_on(self.client().send_log_async(entry))
+    }
+
+    /// Send a metric and block until the API accepts it.
+    pub fn send_metric_blocking(&self, data: MetricData) -> Result<(), ObservabilityError> {
+        self.runtime
+            .block_on(self.client().send_metrics(vec![data]))
+            .pop()
+            .unwrap_or(Ok(()))
+    }
+
+    /// Send a trace span and block until the API accepts it.
+    ///
+    /// Spans dropped by the client's `TraceSampler` return `Ok(())` right away.
+    pub fn send_trace_blocking(&self, span: TraceSpan) -> Result<(), ObservabilityError> {
+        if !self.client().trace_sampled(&span) {
+            return Ok(());
+        }
+        self.runtime.block_on(
+            self.client()
+                .send_trace_span_impl(self.client().prepare_span(span)),
+        )
+    }
+
+    pub fn health_check_blocking(&self) -> Result<HealthStatus, ObservabilityError> {
+        self.runtime.block_on(self.client().health_check())
+    }
+
+    /// Drain the background queue and stop the worker (see `ObservabilityClient::shutdown`).
+    pub fn shutdown(mut self) -> Result<(), ObservabilityError> {
+        match self.inner.take() {
+            Some(inner) => self.runtime.block_on(inner.shutdown()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for BlockingObservabilityClient {
+    /// Flush queued items while the runtime the worker runs on is still alive.
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let _ = self.runtime.block_on(inner.shutdown());
+        }
+    }
+}
+
+fn build_runtime() -> Result<Runtime, ObservabilityError> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .map_err(ObservabilityError::from)
+}
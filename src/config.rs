@@ -0,0 +1,161 @@
+//! Client settings read from environment variables, for twelve-factor deployments.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::helpers::env_var_getter::EnvVarGetter;
+use crate::helpers::gcp_config;
+use crate::{DropPolicy, MonitoredResource, ObservabilityError, RateLimit, Severity};
+
+/// Project id; falls back to `GOOGLE_CLOUD_PROJECT`, `CLOUDSDK_CORE_PROJECT`
+/// and `GCLOUD_PROJECT`.
+pub const GCP_OBS_PROJECT_ID: &str = "GCP_OBS_PROJECT_ID";
+/// Default service name for log entries.
+pub const GCP_OBS_SERVICE_NAME: &str = "GCP_OBS_SERVICE_NAME";
+/// Drop log entries below this severity, e.g. `WARNING`.
+pub const GCP_OBS_MIN_SEVERITY: &str = "GCP_OBS_MIN_SEVERITY";
+/// Mirror log entries at or above this severity through the `log` crate.
+pub const GCP_OBS_LOCAL_MIRROR: &str = "GCP_OBS_LOCAL_MIRROR";
+/// Minimum interval between API requests, in milliseconds.
+pub const GCP_OBS_RATE_LIMIT_MS: &str = "GCP_OBS_RATE_LIMIT_MS";
+/// Requests allowed in a burst on top of `GCP_OBS_RATE_LIMIT_MS` (default 1).
+pub const GCP_OBS_RATE_LIMIT_BURST: &str = "GCP_OBS_RATE_LIMIT_BURST";
+/// Monitored resource type; `detect` (the default) auto-detects it.
+pub const GCP_OBS_RESOURCE_TYPE: &str = "GCP_OBS_RESOURCE_TYPE";
+/// Extra resource labels as `key=value` pairs separated by commas.
+pub const GCP_OBS_RESOURCE_LABELS: &str = "GCP_OBS_RESOURCE_LABELS";
+/// Timeout for each HTTP request, in milliseconds.
+pub const GCP_OBS_HTTP_TIMEOUT_MS: &str = "GCP_OBS_HTTP_TIMEOUT_MS";
+/// Background queue capacity.
+pub const GCP_OBS_QUEUE_CAPACITY: &str = "GCP_OBS_QUEUE_CAPACITY";
+/// Requests the background worker keeps in flight.
+pub const GCP_OBS_MAX_CONCURRENCY: &str = "GCP_OBS_MAX_CONCURRENCY";
+/// `block`, `drop_newest`, `drop_oldest` or `error`.
+pub const GCP_OBS_DROP_POLICY: &str = "GCP_OBS_DROP_POLICY";
+/// Gzip large request bodies (`true`/`false`).
+pub const GCP_OBS_COMPRESSION: &str = "GCP_OBS_COMPRESSION";
+/// Project billed for quota (`x-goog-user-project`).
+pub const GCP_OBS_QUOTA_PROJECT: &str = "GCP_OBS_QUOTA_PROJECT";
+
+/// Client settings, usually read with `from_env`
+///
+/// Unset fields keep the builder's values. Apply with
+/// `ObservabilityClientBuilder::with_config`. Credentials still come from
+/// `GOOGLE_APPLICATION_CREDENTIALS`.
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), gcp_rust_tools::ObservabilityError> {
+/// use gcp_rust_tools::{ObservabilityClient, ObservabilityConfig};
+///
+/// let client = ObservabilityClient::builder()
+///     .with_config(ObservabilityConfig::from_env()?)
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObservabilityConfig {
+    pub project_id: Option<String>,
+    pub service_name: Option<String>,
+    pub min_severity: Option<Severity>,
+    pub local_mirror: Option<Severity>,
+    pub rate_limit: Option<RateLimit>,
+    /// `None` auto-detects the resource.
+    pub resource: Option<MonitoredResource>,
+    pub resource_labels: HashMap<String, String>,
+    pub http_timeout: Option<Duration>,
+    pub queue_capacity: Option<usize>,
+    pub max_concurrency: Option<usize>,
+    pub drop_policy: Option<DropPolicy>,
+    pub compression: Option<bool>,
+    pub quota_project: Option<String>,
+}
+
+impl ObservabilityConfig {
+    /// Read every `GCP_OBS_*` variable; unset or empty ones are left as `None`.
+    ///
+    /// Fails with `SetupError` naming the variable when a value can't be parsed.
+    pub fn from_env() -> Result<Self, ObservabilityError> {
+        let rate_limit = match optional(GCP_OBS_RATE_LIMIT_MS, EnvVarGetter::get_parsed::<u64>)? {
+            Some(interval_ms) => {
+                let burst = optional(GCP_OBS_RATE_LIMIT_BURST, EnvVarGetter::get_parsed::<u32>)?
+                    .unwrap_or(1);
+                Some(RateLimit::new(1000.0 / interval_ms.max(1) as f64, burst))
+            }
+            None => None,
+        };
+        let resource = optional(GCP_OBS_RESOURCE_TYPE, EnvVarGetter::get)?
+            .filter(|resource_type| resource_type != "detect")
+            .map(|resource_type| MonitoredResource::new(resource_type, HashMap::new()));
+
+        Ok(Self {
+            project_id: optional(GCP_OBS_PROJECT_ID, EnvVarGetter::get)?
+                .or_else(gcp_config::project_id_from_env),
+            service_name: optional(GCP_OBS_SERVICE_NAME, EnvVarGetter::get)?,
+            min_severity: optional(GCP_OBS_MIN_SEVERITY, severity)?,
+            local_mirror: optional(GCP_OBS_LOCAL_MIRROR, severity)?,
+            rate_limit,
+            resource,
+            resource_labels: optional(GCP_OBS_RESOURCE_LABELS, labels)?.unwrap_or_default(),
+            http_timeout: optional(GCP_OBS_HTTP_TIMEOUT_MS, EnvVarGetter::get_parsed::<u64>)?
+                .map(Duration::from_millis),
+            queue_capacity: optional(GCP_OBS_QUEUE_CAPACITY, EnvVarGetter::get_parsed)?,
+            max_concurrency: optional(GCP_OBS_MAX_CONCURRENCY, EnvVarGetter::get_parsed)?,
+            drop_policy: optional(GCP_OBS_DROP_POLICY, drop_policy)?,
+            compression: optional(GCP_OBS_COMPRESSION, EnvVarGetter::get_bool)?,
+            quota_project: optional(GCP_OBS_QUOTA_PROJECT, EnvVarGetter::get)?,
+        })
+    }
+}
+
+/// `None` when `key` is unset or empty, otherwise the parsed value.
+fn optional<T>(
+    key: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, ObservabilityError> {
+    if EnvVarGetter::get_or(key, "").is_empty() {
+        return Ok(None);
+    }
+    parse(key).map(Some).map_err(ObservabilityError::SetupError)
+}
+
+fn severity(key: &str) -> Result<Severity, String> {
+    let val = EnvVarGetter::get(key)?;
+    Severity::parse(&val).ok_or_else(|| {
+        format!(
+            "Environment variable '{}' has invalid value '{}': expected a Cloud Logging severity such as INFO or ERROR",
+            key, val
+        )
+    })
+}
+
+fn drop_policy(key: &str) -> Result<DropPolicy, String> {
+    let val = EnvVarGetter::get(key)?;
+    match val.to_lowercase().as_str() {
+        "block" => Ok(DropPolicy::Block),
+        "drop_newest" => Ok(DropPolicy::DropNewest),
+        "drop_oldest" => Ok(DropPolicy::DropOldest),
+        "error" => Ok(DropPolicy::Error),
+        _ => Err(format!(
+            "Environment variable '{}' has invalid value '{}': expected block, drop_newest, drop_oldest or error",
+            key, val
+        )),
+    }
+}
+
+fn labels(key: &str) -> Result<HashMap<String, String>, String> {
+    let val = EnvVarGetter::get(key)?;
+    val.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) if !k.trim().is_empty() => {
+                Ok((k.trim().to_string(), v.trim().to_string()))
+            }
+            _ => Err(format!(
+                "Environment variable '{}' has invalid entry '{}': expected key=value",
+                key, pair
+            )),
+        })
+        .collect()
+}
@@ -0,0 +1,77 @@
+//! Gauge callbacks polled and exported on an interval by the client.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Weak};
+use std::time::Duration;
+
+use crate::{MetricData, ObservabilityClient, Worker};
+
+/// How often registered gauges are polled and sent by default.
+pub const DEFAULT_GAUGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reads the current point of one polled metric.
+pub(crate) type GaugeCallback = Box<dyn Fn() -> MetricData + Send + Sync>;
+
+/// Gauges registered on a client, shared by all of its clones.
+#[derive(Default)]
+pub(crate) struct GaugeRegistry {
+    gauges: Mutex<Vec<GaugeCallback>>,
+    exporter_started: AtomicBool,
+}
+
+impl GaugeRegistry {
+    pub(crate) fn register(&self, callback: GaugeCallback) {
+        self.gauges.lock().unwrap().push(callback);
+    }
+
+    /// One point per registered callback, read now.
+    pub(crate) fn collect(&self) -> Vec<MetricData> {
+        self.gauges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|callback| callback())
+            .collect()
+    }
+
+    /// Whether the exporter still has to be started (true only once).
+    pub(crate) fn claim_exporter(&self) -> bool {
+        !self.exporter_started.swap(true, Ordering::SeqCst)
+    }
+}
+
+/// Poll the gauges every `interval` and send them until every client is dropped
+/// or the worker is shut down.
+///
+/// `client` must not hold the registry or the worker itself, or they would never
+/// be dropped.
+pub(crate) async fn run_exporter(
+    client: ObservabilityClient,
+    registry: Weak<GaugeRegistry>,
+    worker: Option<Weak<Worker>>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately; gauges are first read one interval in.
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(registry) = registry.upgrade() else {
+            return;
+        };
+        if let Some(worker) = &worker {
+            match worker.upgrade() {
+                Some(worker) if !worker.stopped.load(Ordering::SeqCst) => {}
+                _ => return,
+            }
+        }
+        let metrics = registry.collect();
+        drop(registry);
+        for result in client.send_metrics(metrics).await {
+            if let Err(e) = result {
+                log::warn!("Failed to export gauges: {}", e);
+            }
+        }
+    }
+}
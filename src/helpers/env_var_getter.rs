@@ -1,4 +1,5 @@
 use std::env;
+use std::str::FromStr;
 
 pub struct EnvVarGetter;
 
@@ -8,4 +9,40 @@ impl EnvVarGetter {
             .map(|val| val.trim().to_string())
             .map_err(|_| format!("Environment variable '{}' is not set", key))
     }
+
+    /// The trimmed value, or `default` when the variable is unset or empty.
+    pub fn get_or(key: &str, default: impl Into<String>) -> String {
+        Self::get(key)
+            .ok()
+            .filter(|val| !val.is_empty())
+            .unwrap_or_else(|| default.into())
+    }
+
+    /// The value parsed as `T`, e.g. `EnvVarGetter::get_parsed::<u64>("TIMEOUT_MS")`.
+    pub fn get_parsed<T>(key: &str) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let val = Self::get(key)?;
+        val.parse().map_err(|e| {
+            format!(
+                "Environment variable '{}' has invalid value '{}': {}",
+                key, val, e
+            )
+        })
+    }
+
+    /// The value as a flag: `true`/`1`/`yes` or `false`/`0`/`no`, case-insensitive.
+    pub fn get_bool(key: &str) -> Result<bool, String> {
+        let val = Self::get(key)?;
+        match val.to_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(true),
+            "false" | "0" | "no" => Ok(false),
+            _ => Err(format!(
+                "Environment variable '{}' has invalid value '{}': expected true/1/yes or false/0/no",
+                key, val
+            )),
+        }
+    }
 }
@@ -1,5 +1,7 @@
 use std::env;
 
+use super::env_var_getter::EnvVarGetter;
+
 /// Standard env var used by Google SDKs to locate the service account JSON.
 pub const GOOGLE_APPLICATION_CREDENTIALS: &str = "GOOGLE_APPLICATION_CREDENTIALS";
 
@@ -9,6 +11,29 @@ pub const GOOGLE_CREDENTIALS: &str = "GOOGLE_CREDENTIALS";
 /// Standard env var used by many GCP libraries/runtimes.
 pub const GOOGLE_CLOUD_PROJECT: &str = "GOOGLE_CLOUD_PROJECT";
 
+/// gcloud's own project setting, common in CI.
+pub const CLOUDSDK_CORE_PROJECT: &str = "CLOUDSDK_CORE_PROJECT";
+
+/// Legacy project env var still set by older tooling.
+pub const GCLOUD_PROJECT: &str = "GCLOUD_PROJECT";
+
+/// Env vars checked for the project id, in order of precedence.
+pub const PROJECT_ID_ENV_VARS: [&str; 3] =
+    [GOOGLE_CLOUD_PROJECT, CLOUDSDK_CORE_PROJECT, GCLOUD_PROJECT];
+
+/// Standard env var pointing the Pub/Sub client at a local emulator (`host:port`).
+pub const PUBSUB_EMULATOR_HOST: &str = "PUBSUB_EMULATOR_HOST";
+
+/// Project used in emulator mode when none is provided or set in the environment.
+pub const EMULATOR_PROJECT_ID: &str = "local-project";
+
+pub fn pubsub_emulator_host() -> Option<String> {
+    env::var(PUBSUB_EMULATOR_HOST)
+        .ok()
+        .map(|val| val.trim().to_string())
+        .filter(|val| !val.is_empty())
+}
+
 pub fn credentials_path_from_env() -> Result<String, String> {
     let candidates = [GOOGLE_APPLICATION_CREDENTIALS, GOOGLE_CREDENTIALS];
 
@@ -27,18 +52,19 @@ pub fn credentials_path_from_env() -> Result<String, String> {
     ))
 }
 
-fn project_id_from_env() -> Option<String> {
-    match env::var(GOOGLE_CLOUD_PROJECT) {
-        Ok(val) => {
-            let trimmed = val.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
-            }
-        }
-        Err(_) => None,
-    }
+/// The first non-empty variable of `PROJECT_ID_ENV_VARS`.
+pub fn project_id_from_env() -> Option<String> {
+    project_id_env_var().map(|(_, project_id)| project_id)
+}
+
+/// Like `project_id_from_env`, also naming the variable it was read from.
+pub fn project_id_env_var() -> Option<(&'static str, String)> {
+    PROJECT_ID_ENV_VARS.iter().find_map(|key| {
+        EnvVarGetter::get(key)
+            .ok()
+            .filter(|val| !val.is_empty())
+            .map(|val| (*key, val))
+    })
 }
 
 pub async fn project_id_from_gcloud() -> Result<String, String> {
@@ -65,6 +91,8 @@ pub async fn project_id_from_gcloud() -> Result<String, String> {
     Ok(project_id.to_string())
 }
 
+/// Project id from (in order): `provided`, `GOOGLE_CLOUD_PROJECT`,
+/// `CLOUDSDK_CORE_PROJECT`, `GCLOUD_PROJECT`, then `gcloud config get-value project`.
 pub async fn resolve_project_id(provided: Option<String>) -> Result<String, String> {
     if let Some(project_id) = provided {
         let trimmed = project_id.trim();
@@ -0,0 +1,118 @@
+//! Local latency aggregation, flushed periodically as a `DISTRIBUTION` metric.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{BucketOptions, DistributionValue, MetricData};
+
+/// Thread-safe latency histogram with exponential buckets, in milliseconds
+///
+/// Share it (e.g. in an `Arc`) across request handlers, `record` each latency and
+/// periodically send `drain_as_metric`, which resets the histogram.
+///
+/// ```rust
+/// use gcp_rust_tools::LatencyHistogram;
+/// use std::time::Duration;
+///
+/// let histogram = LatencyHistogram::new();
+/// histogram.record(Duration::from_millis(42));
+/// let metric = histogram.drain_as_metric("custom.googleapis.com/request_latency");
+/// assert_eq!(metric.value_type, "DISTRIBUTION");
+/// ```
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    scale: f64,
+    growth_factor: f64,
+    count: u32,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    bucket_counts: Vec<i64>,
+    count: i64,
+    mean: f64,
+    /// Running sum of squared deviations from the mean (Welford).
+    m2: f64,
+}
+
+impl State {
+    fn new(num_finite_buckets: u32) -> Self {
+        Self {
+            bucket_counts: vec![0; num_finite_buckets as usize + 2],
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// 20 buckets doubling from 1ms, covering up to about 17 minutes.
+    pub fn new() -> Self {
+        Self::with_buckets(1.0, 2.0, 20)
+    }
+
+    /// Custom exponential buckets: the first finite bucket starts at `scale_ms` and
+    /// each bucket is `growth_factor` times wider than the previous one.
+    ///
+    /// Panics unless `scale_ms > 0`, `growth_factor > 1` and `count >= 1`.
+    pub fn with_buckets(scale_ms: f64, growth_factor: f64, count: u32) -> Self {
+        assert!(scale_ms > 0.0, "scale must be positive");
+        assert!(growth_factor > 1.0, "growth factor must be greater than 1");
+        assert!(count >= 1, "at least one finite bucket is required");
+        Self {
+            scale: scale_ms,
+            growth_factor,
+            count,
+            state: Mutex::new(State::new(count)),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let value = latency.as_secs_f64() * 1000.0;
+        let bucket = self.bucket_index(value);
+
+        let mut state = self.state.lock().unwrap();
+        state.bucket_counts[bucket] += 1;
+        state.count += 1;
+        let delta = value - state.mean;
+        state.mean += delta / state.count as f64;
+        state.m2 += delta * (value - state.mean);
+    }
+
+    /// Everything recorded since the last drain, as a `DISTRIBUTION` gauge.
+    pub fn drain_as_metric(&self, metric_type: impl Into<String>) -> MetricData {
+        let state = std::mem::replace(&mut *self.state.lock().unwrap(), State::new(self.count));
+        MetricData::distribution(
+            metric_type,
+            DistributionValue {
+                count: state.count,
+                mean: state.mean,
+                sum_of_squared_deviation: state.m2,
+                bucket_options: BucketOptions::Exponential {
+                    scale: self.scale,
+                    growth_factor: self.growth_factor,
+                    count: self.count,
+                },
+                bucket_counts: state.bucket_counts,
+                exemplars: Vec::new(),
+            },
+        )
+    }
+
+    /// Index into `bucket_counts`: 0 is underflow, `count + 1` is overflow.
+    fn bucket_index(&self, value: f64) -> usize {
+        if value < self.scale {
+            return 0;
+        }
+        let index = (value / self.scale).log(self.growth_factor).floor() as usize + 1;
+        index.min(self.count as usize + 1)
+    }
+}
@@ -0,0 +1,236 @@
+//! Counter, gauge and timer instruments on top of `MetricData`.
+//!
+//! Each instrument is cheap to clone (clones share the value) and is sent either
+//! on demand with `flush` or every gauge interval once `register`ed.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    validate_metric_labels, Handle, LatencyHistogram, MetricData, ObservabilityClient,
+    ObservabilityError,
+};
+
+type SendResult = Result<(), crossbeam::channel::SendError<Box<dyn Handle>>>;
+
+fn with_labels(metric: MetricData, labels: &HashMap<String, String>) -> MetricData {
+    if labels.is_empty() {
+        metric
+    } else {
+        metric.with_labels(labels.clone())
+    }
+}
+
+/// Monotonic count, sent as an `INT64` gauge of the running total
+///
+/// ```rust,no_run
+/// # fn run(client: &gcp_rust_tools::ObservabilityClient) -> Result<(), gcp_rust_tools::ObservabilityError> {
+/// use gcp_rust_tools::Counter;
+///
+/// let requests = Counter::new("custom.googleapis.com/requests");
+/// requests.register(client)?; // sent every gauge interval
+/// requests.inc();
+/// requests.add(5);
+/// assert_eq!(requests.value(), 6);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Counter {
+    metric_type: String,
+    labels: HashMap<String, String>,
+    total: Arc<AtomicI64>,
+}
+
+impl Counter {
+    pub fn new(metric_type: impl Into<String>) -> Self {
+        Self {
+            metric_type: metric_type.into(),
+            labels: HashMap::new(),
+            total: Arc::default(),
+        }
+    }
+
+    pub fn with_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: i64) {
+        self.total.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn value(&self) -> i64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// The running total as an `INT64` gauge point.
+    pub fn to_metric(&self) -> MetricData {
+        with_labels(
+            MetricData::int64(self.metric_type.clone(), self.value()),
+            &self.labels,
+        )
+    }
+
+    /// Queue the current total now.
+    pub fn flush(&self, client: &ObservabilityClient) -> SendResult {
+        client.send_metric(self.to_metric())
+    }
+
+    /// Send the total every gauge interval of `client` (see `register_gauge`).
+    pub fn register(&self, client: &ObservabilityClient) -> Result<(), ObservabilityError> {
+        validate_metric_labels(&self.labels)?;
+        let counter = self.clone();
+        client.register_polled(Box::new(move || counter.to_metric()))
+    }
+}
+
+/// Last value set, sent as a `DOUBLE` gauge
+#[derive(Debug, Clone)]
+pub struct Gauge {
+    metric_type: String,
+    labels: HashMap<String, String>,
+    /// `f64` bits.
+    value: Arc<AtomicU64>,
+}
+
+impl Gauge {
+    pub fn new(metric_type: impl Into<String>) -> Self {
+        Self {
+            metric_type: metric_type.into(),
+            labels: HashMap::new(),
+            value: Arc::new(AtomicU64::new(0f64.to_bits())),
+        }
+    }
+
+    pub fn with_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn set(&self, value: f64) {
+        self.value.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn value(&self) -> f64 {
+        f64::from_bits(self.value.load(Ordering::Relaxed))
+    }
+
+    pub fn to_metric(&self) -> MetricData {
+        with_labels(
+            MetricData::double(self.metric_type.clone(), self.value()),
+            &self.labels,
+        )
+    }
+
+    /// Queue the current value now.
+    pub fn flush(&self, client: &ObservabilityClient) -> SendResult {
+        client.send_metric(self.to_metric())
+    }
+
+    /// Send the value every gauge interval of `client` (see `register_gauge`).
+    pub fn register(&self, client: &ObservabilityClient) -> Result<(), ObservabilityError> {
+        validate_metric_labels(&self.labels)?;
+        let gauge = self.clone();
+        client.register_polled(Box::new(move || gauge.to_metric()))
+    }
+}
+
+/// Durations aggregated in a `LatencyHistogram`, sent as a `DISTRIBUTION` in
+/// milliseconds
+///
+/// ```rust
+/// use gcp_rust_tools::Timer;
+///
+/// let latency = Timer::new("custom.googleapis.com/request_latency");
+/// {
+///     let _timing = latency.start();
+///     // ... handle the request ...
+/// } // elapsed time recorded here
+/// assert_eq!(latency.to_metric().value_type, "DISTRIBUTION");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timer {
+    metric_type: String,
+    labels: HashMap<String, String>,
+    histogram: Arc<LatencyHistogram>,
+}
+
+impl Timer {
+    /// A timer with the default `LatencyHistogram` buckets.
+    pub fn new(metric_type: impl Into<String>) -> Self {
+        Self::with_histogram(metric_type, LatencyHistogram::new())
+    }
+
+    /// A timer aggregating into `histogram`, e.g. one with custom buckets.
+    pub fn with_histogram(metric_type: impl Into<String>, histogram: LatencyHistogram) -> Self {
+        Self {
+            metric_type: metric_type.into(),
+            labels: HashMap::new(),
+            histogram: Arc::new(histogram),
+        }
+    }
+
+    pub fn with_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Start timing; the elapsed time is recorded when the guard is dropped.
+    pub fn start(&self) -> TimerGuard<'_> {
+        TimerGuard {
+            timer: self,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn record(&self, elapsed: Duration) {
+        self.histogram.record(elapsed);
+    }
+
+    /// Everything recorded since the last call, which resets the timer.
+    pub fn to_metric(&self) -> MetricData {
+        with_labels(
+            self.histogram.drain_as_metric(self.metric_type.clone()),
+            &self.labels,
+        )
+    }
+
+    /// Queue everything recorded so far now.
+    pub fn flush(&self, client: &ObservabilityClient) -> SendResult {
+        client.send_metric(self.to_metric())
+    }
+
+    /// Send what was recorded every gauge interval of `client` (see
+    /// `register_gauge`).
+    pub fn register(&self, client: &ObservabilityClient) -> Result<(), ObservabilityError> {
+        validate_metric_labels(&self.labels)?;
+        let timer = self.clone();
+        client.register_polled(Box::new(move || timer.to_metric()))
+    }
+}
+
+/// Records the time since `Timer::start` when dropped
+#[derive(Debug)]
+pub struct TimerGuard<'a> {
+    timer: &'a Timer,
+    started: Instant,
+}
+
+impl TimerGuard<'_> {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Drop for TimerGuard<'_> {
+    fn drop(&mut self) {
+        self.timer.record(self.started.elapsed());
+    }
+}
@@ -0,0 +1,122 @@
+//! Builder for Cloud Logging advanced filters, for `read_logs` and `stream_logs`.
+
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+use crate::Severity;
+
+/// Cloud Logging filter built from restrictions that are all required to match
+///
+/// String values are quoted and escaped, so user input can't change the
+/// structure of the filter.
+///
+/// ```rust
+/// use gcp_rust_tools::{LogFilter, Severity};
+///
+/// let filter = LogFilter::new().severity_at_least(Severity::Error).build();
+/// assert_eq!(filter, "severity>=ERROR");
+///
+/// let filter = LogFilter::new()
+///     .resource_type("cloud_run_revision")
+///     .label_eq("user", "a\"b")
+///     .build();
+/// assert_eq!(filter, r#"resource.type="cloud_run_revision" AND labels.user="a\"b""#);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    restrictions: Vec<String>,
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries at `severity` or above.
+    pub fn severity_at_least(self, severity: Severity) -> Self {
+        self.with(format!("severity>={}", severity.as_str()))
+    }
+
+    /// Entries whose monitored resource is `resource_type`, e.g. `k8s_container`.
+    pub fn resource_type(self, resource_type: &str) -> Self {
+        self.with(format!("resource.type={}", quote(resource_type)))
+    }
+
+    /// Entries whose log ID is `log_name` (any project).
+    pub fn log_name(self, log_name: &str) -> Self {
+        self.with(format!(
+            "logName:{}",
+            quote(&format!("/logs/{}", urlencoding::encode(log_name)))
+        ))
+    }
+
+    /// Entries with the label `key` equal to `value`.
+    pub fn label_eq(self, key: &str, value: &str) -> Self {
+        self.with(format!("labels.{}={}", field_name(key), quote(value)))
+    }
+
+    /// Entries logged strictly after `time`.
+    pub fn timestamp_after(self, time: SystemTime) -> Self {
+        self.with(format!("timestamp>{}", quote(&rfc3339(time))))
+    }
+
+    /// Entries logged strictly before `time`.
+    pub fn timestamp_before(self, time: SystemTime) -> Self {
+        self.with(format!("timestamp<{}", quote(&rfc3339(time))))
+    }
+
+    /// Entries whose `textPayload` contains `text` (case-insensitive, as Cloud
+    /// Logging's `:` operator is).
+    pub fn text_contains(self, text: &str) -> Self {
+        self.with(format!("textPayload:{}", quote(text)))
+    }
+
+    /// The filter string; empty when no restriction was added (matches everything).
+    pub fn build(&self) -> String {
+        self.restrictions.join(" AND ")
+    }
+
+    fn with(mut self, restriction: String) -> Self {
+        self.restrictions.push(restriction);
+        self
+    }
+}
+
+impl std::fmt::Display for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.build())
+    }
+}
+
+/// Double-quoted filter string literal with `\` and `"` escaped.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Field name as is when it's a plain identifier, quoted otherwise (e.g. `"k8s-pod/app"`).
+fn field_name(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+}
@@ -0,0 +1,156 @@
+//! Logging convenience macros.
+//!
+//! Every macro records the calling file, line and function as the entry's
+//! Cloud Logging `sourceLocation`.
+//!
+//! `gcp_log!` and its severity shorthands only enqueue the entry on the background
+//! worker: they do not `.await`, so they work in sync code such as `Drop` impls and
+//! panic hooks. The `_await` variants send directly and wait for delivery
+//! confirmation, so they can only be used from async code.
+
+/// Name of the enclosing function, e.g. `my_crate::handlers::create_user`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gcp_function_name {
+    () => {{
+        fn f() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            ::std::any::type_name::<T>()
+        }
+        let name = type_name_of(f);
+        let name = name.strip_suffix("::f").unwrap_or(name);
+        name.trim_end_matches("::{{closure}}")
+    }};
+}
+
+/// Apply one `key = value` macro field to a `LogEntry`.
+///
+/// `service` sets the service name; any other key becomes a label.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gcp_log_field {
+    ($entry:expr, service, $value:expr) => {
+        $entry.with_service_name(($value).to_string())
+    };
+    ($entry:expr, $key:ident, $value:expr) => {
+        $entry.with_label(stringify!($key), ($value).to_string())
+    };
+}
+
+/// Build a `LogEntry` with a formatted message and the caller's source location.
+///
+/// Optional `key = value` pairs may precede the message: `service` sets the
+/// service name and every other key becomes a label.
+///
+/// ```rust,no_run
+/// use gcp_rust_tools::gcp_log_entry;
+///
+/// let entry = gcp_log_entry!("INFO", "user {} logged in", 42);
+/// let entry = gcp_log_entry!("INFO", service = "api", user_id = 42, "request done");
+/// ```
+#[macro_export]
+macro_rules! gcp_log_entry {
+    (@fields ($severity:expr) [$($fields:tt)*] $key:ident = $value:expr, $($rest:tt)+) => {
+        $crate::gcp_log_entry!(@fields ($severity) [$($fields)* ($key, $value)] $($rest)+)
+    };
+    (@fields ($severity:expr) [$(($key:ident, $value:expr))*] $($arg:tt)+) => {{
+        let entry = $crate::LogEntry::new($severity, format!($($arg)+)).with_source_location(
+            file!(),
+            line!(),
+            $crate::__gcp_function_name!(),
+        );
+        $(let entry = $crate::__gcp_log_field!(entry, $key, $value);)*
+        entry
+    }};
+    ($severity:expr, $($rest:tt)+) => {
+        $crate::gcp_log_entry!(@fields ($severity) [] $($rest)+)
+    };
+}
+
+/// Queue a log with a custom severity.
+///
+/// Expands to the synchronous `client.send_log(...)` and evaluates to its `Result`. Accepts the
+/// same `key = value` fields as `gcp_log_entry!`, e.g.
+/// `gcp_info!(client, service = "api", user_id = 42, "request done")`.
+#[macro_export]
+macro_rules! gcp_log {
+    ($client:expr, $severity:expr, $($arg:tt)+) => {
+        $client.send_log($crate::gcp_log_entry!($severity, $($arg)+))
+    };
+}
+
+/// Queue a `DEBUG` log.
+#[macro_export]
+macro_rules! gcp_debug {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log!($client, "DEBUG", $($arg)+)
+    };
+}
+
+/// Queue an `INFO` log.
+#[macro_export]
+macro_rules! gcp_info {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log!($client, "INFO", $($arg)+)
+    };
+}
+
+/// Queue a `WARNING` log.
+#[macro_export]
+macro_rules! gcp_warn {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log!($client, "WARNING", $($arg)+)
+    };
+}
+
+/// Queue an `ERROR` log.
+#[macro_export]
+macro_rules! gcp_error {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log!($client, "ERROR", $($arg)+)
+    };
+}
+
+/// Send a log with a custom severity and wait for delivery.
+///
+/// Expands to `client.send_log_async(...).await`.
+#[macro_export]
+macro_rules! gcp_log_await {
+    ($client:expr, $severity:expr, $($arg:tt)+) => {
+        $client
+            .send_log_async($crate::gcp_log_entry!($severity, $($arg)+))
+            .await
+    };
+}
+
+/// Send a `DEBUG` log and wait for delivery.
+#[macro_export]
+macro_rules! gcp_debug_await {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log_await!($client, "DEBUG", $($arg)+)
+    };
+}
+
+/// Send an `INFO` log and wait for delivery.
+#[macro_export]
+macro_rules! gcp_info_await {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log_await!($client, "INFO", $($arg)+)
+    };
+}
+
+/// Send a `WARNING` log and wait for delivery.
+#[macro_export]
+macro_rules! gcp_warn_await {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log_await!($client, "WARNING", $($arg)+)
+    };
+}
+
+/// Send an `ERROR` log and wait for delivery.
+#[macro_export]
+macro_rules! gcp_error_await {
+    ($client:expr, $($arg:tt)+) => {
+        $crate::gcp_log_await!($client, "ERROR", $($arg)+)
+    };
+}
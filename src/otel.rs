@@ -0,0 +1,113 @@
+//! OpenTelemetry span exporter backed by Cloud Trace.
+//!
+//! Enabled with the `opentelemetry` feature. Plug `CloudTraceExporter` into an
+//! `opentelemetry_sdk` tracer provider (typically behind a batch span processor)
+//! to ship OpenTelemetry spans through this crate's trace path.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use opentelemetry::trace::{ExportError, SpanId, SpanKind as OtelSpanKind, Status, TraceError};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{ObservabilityClient, ObservabilityError, SpanKind, StatusCode, TraceSpan};
+
+impl ExportError for ObservabilityError {
+    fn exporter_name(&self) -> &'static str {
+        "gcp-cloud-trace"
+    }
+}
+
+/// Exports OpenTelemetry `SpanData` to Cloud Trace.
+///
+/// Each batch handed over by the SDK is written with a single `traces:batchWrite` call.
+pub struct CloudTraceExporter {
+    client: ObservabilityClient,
+    is_shutdown: bool,
+}
+
+impl CloudTraceExporter {
+    pub fn new(client: ObservabilityClient) -> Self {
+        Self {
+            client,
+            is_shutdown: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for CloudTraceExporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CloudTraceExporter")
+            .field("is_shutdown", &self.is_shutdown)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SpanExporter for CloudTraceExporter {
+    fn export(
+        &mut self,
+        batch: Vec<SpanData>,
+    ) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+        if self.is_shutdown {
+            return Box::pin(async { Err(TraceError::from("Cloud Trace exporter is shut down")) });
+        }
+        let client = self.client.clone();
+        let spans: Vec<TraceSpan> = batch
+            .into_iter()
+            .map(|data| client.prepare_span(span_from_otel(data)))
+            .collect();
+        Box::pin(async move {
+            if spans.is_empty() {
+                return Ok(());
+            }
+            client
+                .send_trace_spans_impl(spans)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn shutdown(&mut self) {
+        self.is_shutdown = true;
+    }
+
+    /// Spans are written during `export`, so nothing is buffered here.
+    fn force_flush(&mut self) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Convert an OpenTelemetry span into this crate's `TraceSpan`.
+pub fn span_from_otel(data: SpanData) -> TraceSpan {
+    let duration = data
+        .end_time
+        .duration_since(data.start_time)
+        .unwrap_or_default();
+
+    let mut span = TraceSpan::new(
+        data.span_context.trace_id().to_string(),
+        data.span_context.span_id().to_string(),
+        data.name.into_owned(),
+        data.start_time,
+        duration,
+    )
+    .with_kind(match data.span_kind {
+        OtelSpanKind::Client => SpanKind::Client,
+        OtelSpanKind::Server => SpanKind::Server,
+        OtelSpanKind::Producer => SpanKind::Producer,
+        OtelSpanKind::Consumer => SpanKind::Consumer,
+        OtelSpanKind::Internal => SpanKind::Internal,
+    });
+
+    if data.parent_span_id != SpanId::INVALID {
+        span = span.with_parent_span_id(data.parent_span_id.to_string());
+    }
+    for kv in data.attributes {
+        span = span.with_attribute(kv.key.as_str(), kv.value.as_str());
+    }
+    match data.status {
+        Status::Error { description } => span.with_status(StatusCode::Unknown, description),
+        Status::Ok => span.with_status(StatusCode::Ok, ""),
+        Status::Unset => span,
+    }
+}
@@ -1,18 +1,360 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::helpers::gcp_config;
+use crate::TraceContext;
 use google_cloud_auth::credentials::CredentialsFile;
 use google_cloud_googleapis::pubsub::v1::PubsubMessage;
 use google_cloud_pubsub::client::{Client, ClientConfig};
 use google_cloud_pubsub::publisher::Publisher;
+use google_cloud_pubsub::subscriber::ReceivedMessage;
 use google_cloud_pubsub::subscription::{Subscription, SubscriptionConfig};
 
-use log::{debug, error, info};
+use futures_util::{Stream, StreamExt};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+pub use tokio_util::sync::CancellationToken;
+
+/// Message attribute carrying the W3C `traceparent`, as used by Google's client libraries.
+pub const TRACEPARENT_ATTRIBUTE: &str = "googclient_traceparent";
+
+/// Longest ordering key Pub/Sub accepts, in bytes.
+pub const MAX_ORDERING_KEY_BYTES: usize = 1024;
+
+/// Ack deadline of the subscriptions `PubSubsStuff` creates, in seconds.
+const ACK_DEADLINE_SECONDS: i32 = 10;
+
+/// How often `receive` pushes back the deadline of a message still being handled.
+const ACK_EXTENSION_PERIOD: Duration = Duration::from_secs(5);
+
+/// Default limit on how long `receive` keeps extending a message's ack deadline.
+pub const DEFAULT_MAX_ACK_EXTENSION: Duration = Duration::from_secs(60 * 60);
+
+/// Store `context` in message attributes under `key` (usually `TRACEPARENT_ATTRIBUTE`).
+pub fn inject_trace_context(
+    attributes: &mut HashMap<String, String>,
+    context: &TraceContext,
+    key: &str,
+) {
+    attributes.insert(key.to_string(), context.to_traceparent());
+}
+
+/// Read a trace context stored by `inject_trace_context`, if present and valid.
+pub fn extract_trace_context(
+    attributes: &HashMap<String, String>,
+    key: &str,
+) -> Option<TraceContext> {
+    attributes
+        .get(key)
+        .and_then(|value| TraceContext::parse_traceparent(value))
+}
+
+/// Wire format of message payloads, for `PubSubsStuff::publish_with_codec`
+///
+/// Implement it for Protobuf, MessagePack or JSON with custom settings:
+///
+/// ```rust
+/// use gcp_rust_tools::pubsub::MessageCodec;
+///
+/// struct Utf8Codec;
+///
+/// impl MessageCodec<str> for Utf8Codec {
+///     type Error = std::convert::Infallible;
+///
+///     fn encode(&self, payload: &str) -> Result<Vec<u8>, Self::Error> {
+///         Ok(payload.as_bytes().to_vec())
+///     }
+/// }
+/// ```
+pub trait MessageCodec<T: ?Sized> {
+    type Error: std::fmt::Display;
+
+    fn encode(&self, payload: &T) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Encodes payloads with `serde_json`, as `create_message` and the other publish
+/// helpers do.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl<T: Serialize + ?Sized> MessageCodec<T> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn encode(&self, payload: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_json::to_vec(payload)
+    }
+}
+
+/// Errors returned by the `PubSubsStuff` lookup APIs
+#[derive(Debug)]
+pub enum PubSubError {
+    /// A Pub/Sub API call failed.
+    ApiError(String),
+    /// The topic or subscription is not managed by this `PubSubsStuff`.
+    NotFound(String),
+    /// The payload could not be serialized.
+    SerializationError(String),
+    /// A subscription option is invalid (e.g. a non-HTTPS push endpoint).
+    InvalidConfig(String),
+    /// An ordering key is empty or longer than `MAX_ORDERING_KEY_BYTES`.
+    InvalidOrderingKey(String),
+}
+
+impl std::fmt::Display for PubSubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PubSubError::ApiError(msg) => write!(f, "Pub/Sub API error: {}", msg),
+            PubSubError::NotFound(name) => write!(f, "Pub/Sub resource not found: {}", name),
+            PubSubError::SerializationError(msg) => write!(f, "Serialization error: {}", msg),
+            PubSubError::InvalidConfig(msg) => write!(f, "Invalid Pub/Sub configuration: {}", msg),
+            PubSubError::InvalidOrderingKey(msg) => write!(f, "Invalid ordering key: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PubSubError {}
+
+/// Options applied to the subscriptions managed by `PubSubsStuff`
+#[derive(Debug, Clone)]
+pub struct SubscriptionOptions {
+    /// Create subscriptions with exactly-once delivery. `Delivery::ack` then only
+    /// succeeds once Pub/Sub has confirmed the acknowledgement.
+    pub exactly_once_delivery: bool,
+    /// Deliver messages to an HTTPS endpoint instead of creating pull subscriptions.
+    pub push_config: Option<PushConfig>,
+    /// Delete the subscriptions this instance created when it is dropped.
+    pub cleanup_on_drop: bool,
+    /// How long `receive` keeps extending the ack deadline of a message whose
+    /// handler is still running. `Duration::ZERO` disables extension.
+    pub max_ack_extension: Duration,
+}
+
+impl Default for SubscriptionOptions {
+    fn default() -> Self {
+        Self {
+            exactly_once_delivery: false,
+            push_config: None,
+            cleanup_on_drop: false,
+            max_ack_extension: DEFAULT_MAX_ACK_EXTENSION,
+        }
+    }
+}
+
+impl SubscriptionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_exactly_once_delivery(mut self, enabled: bool) -> Self {
+        self.exactly_once_delivery = enabled;
+        self
+    }
+
+    pub fn with_push_config(mut self, push_config: PushConfig) -> Self {
+        self.push_config = Some(push_config);
+        self
+    }
+
+    /// Delete created subscriptions on drop; see `PubSubsStuff::delete_subscriptions`.
+    pub fn with_cleanup_on_drop(mut self, enabled: bool) -> Self {
+        self.cleanup_on_drop = enabled;
+        self
+    }
+
+    /// Keep extending the ack deadline (10s) of a message while its `receive`
+    /// handler runs, for at most `max` (defaults to `DEFAULT_MAX_ACK_EXTENSION`).
+    ///
+    /// Without it, a handler taking longer than the deadline gets its message
+    /// redelivered while it is still processing it.
+    pub fn with_max_ack_extension(mut self, max: Duration) -> Self {
+        self.max_ack_extension = max;
+        self
+    }
+}
+
+/// Push delivery settings, e.g. for a Cloud Run consumer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushConfig {
+    /// HTTPS URL Pub/Sub POSTs each message to.
+    pub endpoint: String,
+    /// Endpoint attributes, e.g. `x-goog-version`.
+    pub attributes: HashMap<String, String>,
+    /// Sign push requests with an OIDC token (authenticated push).
+    pub oidc_token: Option<OidcToken>,
+}
+
+/// Service account (and audience) Pub/Sub uses to sign push requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcToken {
+    pub service_account_email: String,
+    /// Token audience; Pub/Sub uses the endpoint URL when unset.
+    pub audience: Option<String>,
+}
+
+impl PushConfig {
+    /// Push to `endpoint`, which must be an `https://` URL.
+    pub fn new(endpoint: impl Into<String>) -> Result<Self, PubSubError> {
+        let config = Self {
+            endpoint: endpoint.into(),
+            attributes: HashMap::new(),
+            oidc_token: None,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// Authenticate push requests with an OIDC token for `service_account_email`.
+    pub fn with_oidc_token(
+        mut self,
+        service_account_email: impl Into<String>,
+        audience: Option<String>,
+    ) -> Self {
+        self.oidc_token = Some(OidcToken {
+            service_account_email: service_account_email.into(),
+            audience,
+        });
+        self
+    }
+
+    fn validate(&self) -> Result<(), PubSubError> {
+        if !self.endpoint.starts_with("https://") {
+            return Err(PubSubError::InvalidConfig(format!(
+                "push endpoint '{}' must use https://",
+                self.endpoint
+            )));
+        }
+        Ok(())
+    }
+
+    fn to_api(&self) -> google_cloud_googleapis::pubsub::v1::PushConfig {
+        use google_cloud_googleapis::pubsub::v1::push_config;
+
+        google_cloud_googleapis::pubsub::v1::PushConfig {
+            push_endpoint: self.endpoint.clone(),
+            attributes: self.attributes.clone(),
+            authentication_method: self.oidc_token.as_ref().map(|token| {
+                push_config::AuthenticationMethod::OidcToken(push_config::OidcToken {
+                    service_account_email: token.service_account_email.clone(),
+                    audience: token.audience.clone().unwrap_or_default(),
+                })
+            }),
+            wrapper: None,
+        }
+    }
+}
+
+/// A message handed to a `PubSubsStuff::receive` handler.
+///
+/// The handler decides when the message counts as processed by calling `ack`.
+/// Messages that are neither acked nor nacked when the handler returns are nacked
+/// so Pub/Sub redelivers them.
+pub struct Delivery {
+    message: Arc<ReceivedMessage>,
+    settled: Arc<AtomicBool>,
+    exactly_once: bool,
+}
+
+impl Delivery {
+    pub fn message(&self) -> &PubsubMessage {
+        &self.message.message
+    }
+
+    /// Deserialize the JSON payload.
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.message.message.data)
+    }
+
+    /// Trace context the publisher attached with `publish_with_trace`, for continuing
+    /// the trace in the handler (`context.child(...)`).
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        self.trace_context_from(TRACEPARENT_ATTRIBUTE)
+    }
+
+    /// Like `trace_context`, reading a custom attribute key.
+    pub fn trace_context_from(&self, key: &str) -> Option<TraceContext> {
+        extract_trace_context(&self.message.message.attributes, key)
+    }
+
+    /// Whether the subscription was configured for exactly-once delivery.
+    pub fn is_exactly_once(&self) -> bool {
+        self.exactly_once
+    }
+
+    /// Acknowledge the message.
+    ///
+    /// With exactly-once delivery an `Ok` means Pub/Sub confirmed the ack and the
+    /// message will not be redelivered; an `Err` means it may be, so the handler
+    /// should avoid (or roll back) side effects it can't repeat.
+    pub async fn ack(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.settled.store(true, Ordering::SeqCst);
+        self.message.ack().await.map_err(|e| e.into())
+    }
+
+    /// Ask Pub/Sub to redeliver the message.
+    pub async fn nack(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.settled.store(true, Ordering::SeqCst);
+        self.message.nack().await.map_err(|e| e.into())
+    }
+}
+
+/// Settles a message yielded by `PubSubsStuff::subscribe_stream`.
+///
+/// A handle dropped without `ack` or `nack` leaves the message to be redelivered
+/// once its ack deadline expires.
+pub struct AckHandle {
+    message: ReceivedMessage,
+}
+
+impl AckHandle {
+    pub fn message(&self) -> &PubsubMessage {
+        &self.message.message
+    }
+
+    /// Trace context the publisher attached with `publish_with_trace`.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        extract_trace_context(&self.message.message.attributes, TRACEPARENT_ATTRIBUTE)
+    }
+
+    /// Acknowledge the message.
+    pub async fn ack(self) -> Result<(), PubSubError> {
+        self.message
+            .ack()
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("ack: {}", e)))
+    }
+
+    /// Ask Pub/Sub to redeliver the message.
+    pub async fn nack(self) -> Result<(), PubSubError> {
+        self.message
+            .nack()
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("nack: {}", e)))
+    }
+}
+
 pub struct PubSubsStuff {
     pub publishers: Arc<[(String, Publisher)]>,
     pub subscriptions: Arc<[(String, Subscription)]>,
+    options: SubscriptionOptions,
+    client: Client,
+    project_id: String,
+    instance_id: String,
+    /// Subscriptions `new` created (not existing ones it fell back to), by short name.
+    created_subscriptions: Mutex<Vec<String>>,
+    /// One lock per ordering key so `publish_ordered` sends each key's messages in turn.
+    ordering_locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+    /// Set once the missing-ordering-key warning has been logged.
+    warned_missing_key: AtomicBool,
 }
 
 impl PubSubsStuff {
@@ -21,20 +363,62 @@ impl PubSubsStuff {
         instance_id: &str,
         topics: Arc<[&'static str]>,
         subs: Arc<[&'static str]>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::new_with_options(
+            project_id,
+            instance_id,
+            topics,
+            subs,
+            SubscriptionOptions::default(),
+        )
+        .await
+    }
+
+    /// Like `new`, applying `options` to the subscriptions it creates.
+    ///
+    /// Options only take effect for subscriptions created here; existing
+    /// subscriptions keep their server-side configuration.
+    pub async fn new_with_options(
+        project_id: Option<String>,
+        instance_id: &str,
+        topics: Arc<[&'static str]>,
+        subs: Arc<[&'static str]>,
+        options: SubscriptionOptions,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         info!("Initializing PubSub client");
 
-        let key_file_path = gcp_config::credentials_path_from_env().map_err(|e| {
-            let err: Box<dyn std::error::Error + Send + Sync> = e.into();
-            err
-        })?;
+        if let Some(push_config) = &options.push_config {
+            push_config.validate()?;
+        }
 
-        let project_id = gcp_config::resolve_project_id(project_id)
-            .await
-            .map_err(|e| {
+        // In emulator mode the client talks plaintext to PUBSUB_EMULATOR_HOST and
+        // needs neither credentials nor gcloud.
+        let emulator_host = gcp_config::pubsub_emulator_host();
+
+        let key_file_path = match &emulator_host {
+            Some(_) => None,
+            None => Some(gcp_config::credentials_path_from_env().map_err(|e| {
                 let err: Box<dyn std::error::Error + Send + Sync> = e.into();
                 err
-            })?;
+            })?),
+        };
+
+        let project_id = match &emulator_host {
+            Some(host) => {
+                info!("Using Pub/Sub emulator at '{}'", host);
+                project_id
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .or_else(gcp_config::project_id_from_env)
+                    .unwrap_or_else(|| gcp_config::EMULATOR_PROJECT_ID.to_string())
+            }
+            None => gcp_config::resolve_project_id(project_id)
+                .await
+                .map_err(|e| {
+                    let err: Box<dyn std::error::Error + Send + Sync> = e.into();
+                    err
+                })?,
+        };
 
         info!("Using project_id: '{}'", project_id);
 
@@ -60,10 +444,15 @@ impl PubSubsStuff {
             })
             .collect();
 
-        let credentials = CredentialsFile::new_from_file(key_file_path).await?;
-        let config = ClientConfig::default()
-            .with_credentials(credentials)
-            .await?;
+        // `ClientConfig::default` picks up PUBSUB_EMULATOR_HOST on its own.
+        let mut config = ClientConfig {
+            project_id: Some(project_id.clone()),
+            ..ClientConfig::default()
+        };
+        if let Some(key_file_path) = key_file_path {
+            let credentials = CredentialsFile::new_from_file(key_file_path).await?;
+            config = config.with_credentials(credentials).await?;
+        }
         let client = Client::new(config).await?;
 
         /* ---------- Publishers (build → freeze) ---------- */
@@ -81,11 +470,12 @@ impl PubSubsStuff {
         /* ---------- Subscriptions (build → freeze) ---------- */
 
         let mut subscriptions_vec = Vec::with_capacity(expanded_subs.len());
+        let mut created_subscriptions = Vec::new();
 
         for (sub_path, name) in expanded_subs.iter() {
             let sub_config = SubscriptionConfig {
-                push_config: None,
-                ack_deadline_seconds: 10,
+                push_config: options.push_config.as_ref().map(PushConfig::to_api),
+                ack_deadline_seconds: ACK_DEADLINE_SECONDS,
                 retain_acked_messages: false,
                 message_retention_duration: None,
                 labels: Default::default(),
@@ -96,7 +486,7 @@ impl PubSubsStuff {
                 retry_policy: None,
                 detached: false,
                 topic_message_retention_duration: None,
-                enable_exactly_once_delivery: false,
+                enable_exactly_once_delivery: options.exactly_once_delivery,
                 bigquery_config: None,
                 state: 0,
                 cloud_storage_config: None,
@@ -106,7 +496,10 @@ impl PubSubsStuff {
                 .create_subscription(sub_path, "", sub_config, None)
                 .await
             {
-                Ok(sub) => sub,
+                Ok(sub) => {
+                    created_subscriptions.push(name.to_string());
+                    sub
+                }
                 Err(err) => {
                     error!(
                         "Failed to create subscription '{}': {:?}. Falling back.",
@@ -127,6 +520,13 @@ impl PubSubsStuff {
         Ok(Self {
             publishers,
             subscriptions,
+            options,
+            client,
+            project_id,
+            instance_id: instance_id.to_string(),
+            created_subscriptions: Mutex::new(created_subscriptions),
+            ordering_locks: Arc::default(),
+            warned_missing_key: AtomicBool::new(false),
         })
     }
 
@@ -146,14 +546,229 @@ impl PubSubsStuff {
             .map(|(_, s)| s.clone())
     }
 
+    /* ---------- Existence checks ---------- */
+
+    /// Whether the topic `name` (expanded like the `topics` passed to `new`) exists.
+    pub async fn topic_exists(&self, name: &str) -> Result<bool, PubSubError> {
+        self.client
+            .topic(&self.topic_path(name))
+            .exists(None)
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("topic '{}': {}", name, e)))
+    }
+
+    /// Whether the subscription `name` exists.
+    pub async fn subscription_exists(&self, name: &str) -> Result<bool, PubSubError> {
+        self.client
+            .subscription(&self.subscription_path(name))
+            .exists(None)
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("subscription '{}': {}", name, e)))
+    }
+
+    /// Topics of this instance in the project, as the short names passed to `new`.
+    pub async fn list_topics(&self) -> Result<Vec<String>, PubSubError> {
+        let prefix = format!("projects/{}/topics/", self.project_id);
+        let suffix = format!("-{}", self.instance_id);
+        let topics = self
+            .client
+            .get_topics(None)
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("list topics: {}", e)))?;
+        Ok(topics
+            .iter()
+            .filter_map(|t| t.strip_prefix(&prefix)?.strip_suffix(&suffix))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Subscriptions in the project, as short names.
+    pub async fn list_subscriptions(&self) -> Result<Vec<String>, PubSubError> {
+        let subscriptions = self
+            .client
+            .get_subscriptions(None)
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("list subscriptions: {}", e)))?;
+        Ok(subscriptions.iter().map(|s| s.id()).collect())
+    }
+
+    /* ---------- Cleanup ---------- */
+
+    /// Delete the subscriptions this instance created.
+    ///
+    /// Subscriptions that already existed when `new` ran are left alone, so shared
+    /// subscriptions survive ephemeral instances. Safe to call more than once: a
+    /// subscription is forgotten once deleted, and failed deletions are retried on
+    /// the next call.
+    pub async fn delete_subscriptions(&self) -> Result<(), PubSubError> {
+        let names = std::mem::take(&mut *self.created_subscriptions.lock().unwrap());
+        let mut failed = Vec::new();
+        let mut errors = Vec::new();
+        for name in names {
+            let subscription = self.client.subscription(&self.subscription_path(&name));
+            match subscription.delete(None).await {
+                Ok(()) => info!("Deleted subscription '{}'", name),
+                Err(e) => {
+                    errors.push(format!("subscription '{}': {}", name, e));
+                    failed.push(name);
+                }
+            }
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        self.created_subscriptions.lock().unwrap().extend(failed);
+        Err(PubSubError::ApiError(format!(
+            "delete {}",
+            errors.join("; ")
+        )))
+    }
+
+    fn topic_path(&self, name: &str) -> String {
+        format!(
+            "projects/{}/topics/{}-{}",
+            self.project_id, name, self.instance_id
+        )
+    }
+
+    fn subscription_path(&self, name: &str) -> String {
+        format!("projects/{}/subscriptions/{}", self.project_id, name)
+    }
+
+    /* ---------- Receiving ---------- */
+
+    /// Receive messages from a managed subscription until `cancel` is triggered.
+    ///
+    /// `handler` runs for every message and acknowledges it through `Delivery::ack`.
+    /// With `SubscriptionOptions::exactly_once_delivery` the ack is awaited until
+    /// Pub/Sub confirms it, and failures are returned to the handler.
+    pub async fn receive<F, Fut>(
+        &self,
+        sub_name: &str,
+        handler: F,
+        cancel: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: Fn(Delivery) -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let subscription = self
+            .get_subscription(sub_name)
+            .ok_or_else(|| format!("Subscription '{}' not found", sub_name))?;
+        let exactly_once = self.options.exactly_once_delivery;
+        let max_ack_extension = self.options.max_ack_extension;
+
+        subscription
+            .receive(
+                move |message, _cancel| {
+                    let handler = handler.clone();
+                    async move {
+                        let message = Arc::new(message);
+                        let settled = Arc::new(AtomicBool::new(false));
+                        let handling = handler(Delivery {
+                            message: message.clone(),
+                            settled: settled.clone(),
+                            exactly_once,
+                        });
+                        extend_ack_deadline_while(&message, &settled, max_ack_extension, handling)
+                            .await;
+
+                        if !settled.load(Ordering::SeqCst) {
+                            if let Err(e) = message.nack().await {
+                                warn!("Failed to nack unacknowledged message: {:?}", e);
+                            }
+                        }
+                    }
+                },
+                cancel,
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Consume a subscription as a stream of deserialized payloads.
+    ///
+    /// Messages are pulled with streaming pull as the stream is polled, so a slow
+    /// consumer applies backpressure. A message whose payload isn't valid JSON for
+    /// `T` is nacked with a warning and skipped. Dropping the stream stops pulling.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(pubsub: &gcp_rust_tools::pubsub::PubSubsStuff) -> Result<(), gcp_rust_tools::pubsub::PubSubError> {
+    /// use gcp_rust_tools::StreamExt;
+    ///
+    /// let mut events = pubsub.subscribe_stream::<serde_json::Value>("events-sub").await?;
+    /// while let Some((event, ack)) = events.next().await {
+    ///     println!("{}", event);
+    ///     ack.ack().await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_stream<T: DeserializeOwned + Send + 'static>(
+        &self,
+        sub_name: &str,
+    ) -> Result<impl Stream<Item = (T, AckHandle)> + Send + Unpin, PubSubError> {
+        let subscription = self
+            .get_subscription(sub_name)
+            .ok_or_else(|| PubSubError::NotFound(format!("subscription '{}'", sub_name)))?;
+        let messages = subscription
+            .subscribe(None)
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("subscribe to '{}': {}", sub_name, e)))?;
+        let sub_name = sub_name.to_string();
+
+        Ok(Box::pin(messages.filter_map(move |message| {
+            let payload = serde_json::from_slice::<T>(&message.message.data);
+            let sub_name = sub_name.clone();
+            async move {
+                match payload {
+                    Ok(payload) => Some((payload, AckHandle { message })),
+                    Err(e) => {
+                        warn!(
+                            "Nacking message '{}' on '{}': payload does not deserialize: {}",
+                            message.message.message_id, sub_name, e
+                        );
+                        if let Err(e) = message.nack().await {
+                            warn!("Failed to nack undecodable message: {:?}", e);
+                        }
+                        None
+                    }
+                }
+            }
+        })))
+    }
+
     /* ---------- Message helpers ---------- */
 
+    /// Serialize `payload` into a message, with `ordering_key` if given.
+    ///
+    /// A key must be non-empty and at most `MAX_ORDERING_KEY_BYTES`, otherwise this
+    /// fails with `PubSubError::InvalidOrderingKey`. The subscriptions created here
+    /// have message ordering enabled, so the first message without a key logs a
+    /// warning: it won't be delivered in order relative to anything else.
     pub fn create_message<T: Serialize>(
         &self,
         payload: T,
         ordering_key: Option<String>,
-    ) -> Result<PubsubMessage, serde_json::Error> {
-        let data = serde_json::to_vec(&payload)?;
+    ) -> Result<PubsubMessage, PubSubError> {
+        self.create_message_with_codec(&payload, &JsonCodec, ordering_key)
+    }
+
+    /// Like `create_message`, encoding `payload` with `codec`.
+    pub fn create_message_with_codec<T: ?Sized, C: MessageCodec<T>>(
+        &self,
+        payload: &T,
+        codec: &C,
+        ordering_key: Option<String>,
+    ) -> Result<PubsubMessage, PubSubError> {
+        match &ordering_key {
+            Some(key) => validate_ordering_key(key)?,
+            None => self.warn_missing_ordering_key(),
+        }
+        let data = codec
+            .encode(payload)
+            .map_err(|e| PubSubError::SerializationError(e.to_string()))?;
 
         Ok(PubsubMessage {
             data,
@@ -164,6 +779,125 @@ impl PubSubsStuff {
         })
     }
 
+    fn warn_missing_ordering_key(&self) {
+        if self.subscriptions.is_empty() || self.warned_missing_key.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        warn!(
+            "Publishing without an ordering key while subscriptions have message ordering enabled; \
+             such messages are delivered in no particular order"
+        );
+    }
+
+    /// Publish a message with an ordering key and wait for Pub/Sub to accept it.
+    ///
+    /// Messages sharing an ordering key are published one at a time, in call order,
+    /// so they can't race the way independent `publish_fire_and_forget` tasks can.
+    /// Returns the message id.
+    ///
+    /// A failed publish is returned to the caller and the key stays usable: the next
+    /// call for it publishes normally. (The google-cloud-pubsub publisher does not
+    /// pause keys after a failure, so there is no `resume_publish` step needed.)
+    pub async fn publish_ordered<T: Serialize>(
+        &self,
+        topic: &str,
+        payload: T,
+        ordering_key: &str,
+    ) -> Result<String, PubSubError> {
+        let publisher = self
+            .get_publisher(topic)
+            .ok_or_else(|| PubSubError::NotFound(format!("topic '{}'", topic)))?;
+        let message = self.create_message(payload, Some(ordering_key.to_string()))?;
+        self.publish_in_order(topic, &publisher, message).await
+    }
+
+    /// Publish `payload` encoded with `codec` and wait for Pub/Sub to accept it.
+    /// Returns the message id.
+    ///
+    /// With an `ordering_key`, messages are sent in call order like `publish_ordered`.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(pubsub: &gcp_rust_tools::pubsub::PubSubsStuff) -> Result<(), gcp_rust_tools::pubsub::PubSubError> {
+    /// use gcp_rust_tools::pubsub::JsonCodec;
+    ///
+    /// let event = serde_json::json!({"hello": "world"});
+    /// pubsub.publish_with_codec("events", &event, &JsonCodec, None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn publish_with_codec<T: ?Sized, C: MessageCodec<T>>(
+        &self,
+        topic: &str,
+        payload: &T,
+        codec: &C,
+        ordering_key: Option<String>,
+    ) -> Result<String, PubSubError> {
+        let publisher = self
+            .get_publisher(topic)
+            .ok_or_else(|| PubSubError::NotFound(format!("topic '{}'", topic)))?;
+        let message = self.create_message_with_codec(payload, codec, ordering_key)?;
+        if message.ordering_key.is_empty() {
+            return publisher
+                .publish(message)
+                .await
+                .get()
+                .await
+                .map_err(|e| PubSubError::ApiError(format!("publish to '{}': {}", topic, e)));
+        }
+        self.publish_in_order(topic, &publisher, message).await
+    }
+
+    /// Publish a keyed message once earlier messages with the same key are accepted.
+    async fn publish_in_order(
+        &self,
+        topic: &str,
+        publisher: &Publisher,
+        message: PubsubMessage,
+    ) -> Result<String, PubSubError> {
+        let ordering_key = message.ordering_key.clone();
+        let key_lock = self
+            .ordering_locks
+            .lock()
+            .unwrap()
+            .entry(ordering_key.clone())
+            .or_default()
+            .clone();
+        let _guard = key_lock.lock().await;
+
+        publisher.publish(message).await.get().await.map_err(|e| {
+            warn!(
+                "Ordered publish to '{}' failed for key '{}': {:?}",
+                topic, ordering_key, e
+            );
+            PubSubError::ApiError(format!("publish to '{}': {}", topic, e))
+        })
+    }
+
+    /// Publish a message carrying `context` in its `TRACEPARENT_ATTRIBUTE` attribute and
+    /// wait for Pub/Sub to accept it, so the consumer can continue the trace
+    /// (`Delivery::trace_context`). Returns the message id.
+    pub async fn publish_with_trace<T: Serialize>(
+        &self,
+        topic: &str,
+        payload: T,
+        context: &TraceContext,
+    ) -> Result<String, PubSubError> {
+        let publisher = self
+            .get_publisher(topic)
+            .ok_or_else(|| PubSubError::NotFound(format!("topic '{}'", topic)))?;
+        let mut message = self.create_message(payload, None)?;
+        inject_trace_context(&mut message.attributes, context, TRACEPARENT_ATTRIBUTE);
+
+        publisher
+            .publish(message)
+            .await
+            .get()
+            .await
+            .map_err(|e| PubSubError::ApiError(format!("publish to '{}': {}", topic, e)))
+    }
+
+    /// Publish in the background. Errors, including an invalid ordering key, are
+    /// only logged.
     pub async fn publish_fire_and_forget<T: Serialize + Send + 'static>(
         &self,
         topic: &str,
@@ -172,29 +906,107 @@ impl PubSubsStuff {
     ) {
         let publisher = self.get_publisher(topic);
         let topic_name = topic.to_string();
+        let message = self.create_message(payload, ordering_key);
 
         tokio::spawn(async move {
-            match publisher {
-                Some(publisher) => match serde_json::to_vec(&payload) {
-                    Ok(data) => {
-                        let message = PubsubMessage {
-                            data,
-                            attributes: Default::default(),
-                            ordering_key: ordering_key.unwrap_or_default(),
-                            message_id: String::new(),
-                            publish_time: None,
-                        };
-                        publisher.publish(message).await;
-                        debug!("Message published to '{}'", topic_name);
-                    }
-                    Err(e) => error!("Failed to serialize payload: {:?}", e),
-                },
-                None => error!("Publisher '{}' not found", topic_name),
+            match (publisher, message) {
+                (Some(publisher), Ok(message)) => {
+                    publisher.publish(message).await;
+                    debug!("Message published to '{}'", topic_name);
+                }
+                (Some(_), Err(e)) => {
+                    error!("Failed to create message for '{}': {}", topic_name, e)
+                }
+                (None, _) => error!("Publisher '{}' not found", topic_name),
+            }
+        });
+    }
+}
+
+impl Drop for PubSubsStuff {
+    /// With `cleanup_on_drop`, deletes the created subscriptions on a runtime task.
+    ///
+    /// Best effort: `Drop` can't wait, so the deletion is lost if the runtime shuts
+    /// down first. Call `delete_subscriptions` during shutdown to be sure.
+    fn drop(&mut self) {
+        if !self.options.cleanup_on_drop {
+            return;
+        }
+        let names = std::mem::take(&mut *self.created_subscriptions.lock().unwrap());
+        if names.is_empty() {
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            warn!(
+                "PubSubsStuff dropped outside a Tokio runtime; subscriptions {:?} were not deleted",
+                names
+            );
+            return;
+        };
+        let subscriptions: Vec<(String, Subscription)> = names
+            .into_iter()
+            .map(|name| {
+                let subscription = self.client.subscription(&self.subscription_path(&name));
+                (name, subscription)
+            })
+            .collect();
+        handle.spawn(async move {
+            for (name, subscription) in subscriptions {
+                match subscription.delete(None).await {
+                    Ok(()) => info!("Deleted subscription '{}'", name),
+                    Err(e) => warn!("Failed to delete subscription '{}': {:?}", name, e),
+                }
             }
         });
     }
 }
 
+fn validate_ordering_key(key: &str) -> Result<(), PubSubError> {
+    if key.is_empty() {
+        return Err(PubSubError::InvalidOrderingKey(
+            "key is empty; pass None to publish without ordering".to_string(),
+        ));
+    }
+    if key.len() > MAX_ORDERING_KEY_BYTES {
+        return Err(PubSubError::InvalidOrderingKey(format!(
+            "key is {} bytes, the limit is {}",
+            key.len(),
+            MAX_ORDERING_KEY_BYTES
+        )));
+    }
+    Ok(())
+}
+
+/// Run `handling`, pushing back the ack deadline of `message` every
+/// `ACK_EXTENSION_PERIOD` until it completes, the message is settled or
+/// `max_extension` has passed.
+async fn extend_ack_deadline_while<F: Future>(
+    message: &ReceivedMessage,
+    settled: &AtomicBool,
+    max_extension: Duration,
+    handling: F,
+) -> F::Output {
+    tokio::pin!(handling);
+    let started = Instant::now();
+    let mut ticker = tokio::time::interval_at(
+        tokio::time::Instant::now() + ACK_EXTENSION_PERIOD,
+        ACK_EXTENSION_PERIOD,
+    );
+    loop {
+        tokio::select! {
+            output = &mut handling => return output,
+            _ = ticker.tick(), if started.elapsed() < max_extension => {
+                if settled.load(Ordering::SeqCst) {
+                    continue;
+                }
+                if let Err(e) = message.modify_ack_deadline(ACK_DEADLINE_SECONDS).await {
+                    warn!("Failed to extend ack deadline: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
 pub async fn create_pubsub_client(
     project_id: Option<String>,
     instance_id: &str,
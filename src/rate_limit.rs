@@ -0,0 +1,113 @@
+//! Token-bucket limiter for the client's outgoing API requests.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ObservabilityError;
+
+/// What happens when a request arrives and no token is available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    /// Sleep until the next token is available, then proceed (recommended).
+    #[default]
+    Wait,
+    /// Fail immediately with `ObservabilityError::RateLimitError`.
+    Error,
+}
+
+/// Request rate limit applied to every GCP API call made by the client
+///
+/// Allows bursts of up to `burst` requests, refilled at `requests_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub mode: RateLimitMode,
+}
+
+impl RateLimit {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        Self {
+            requests_per_second,
+            burst,
+            mode: RateLimitMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+pub(crate) struct RateLimiter {
+    config: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Fails for a rate that isn't a positive finite number, which could never
+    /// refill the bucket.
+    pub(crate) fn new(config: RateLimit) -> Result<Self, ObservabilityError> {
+        if !(config.requests_per_second.is_finite() && config.requests_per_second > 0.0) {
+            return Err(ObservabilityError::SetupError(format!(
+                "rate limit must be a positive number of requests/s, got {}",
+                config.requests_per_second
+            )));
+        }
+        let config = RateLimit {
+            burst: config.burst.max(1),
+            ..config
+        };
+        Ok(Self {
+            bucket: Mutex::new(Bucket {
+                tokens: config.burst as f64,
+                last_refill: Instant::now(),
+            }),
+            config,
+        })
+    }
+
+    /// Take a token, waiting for one in `RateLimitMode::Wait`.
+    pub(crate) async fn acquire(&self) -> Result<(), ObservabilityError> {
+        loop {
+            let wait = match self.try_take() {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+            if self.config.mode == RateLimitMode::Error {
+                return Err(ObservabilityError::RateLimitError(format!(
+                    "rate limit of {} requests/s exceeded",
+                    self.config.requests_per_second
+                )));
+            }
+            // Up to 10% jitter so concurrent waiters don't all wake at once.
+            let jitter = wait.mul_f64(fastrand::f64() * 0.1);
+            tokio::time::sleep(wait + jitter).await;
+        }
+    }
+
+    /// Take a token, or return how long until one is available.
+    fn try_take(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.requests_per_second)
+            .min(self.config.burst as f64);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.config.requests_per_second,
+            ))
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Scrubbing of secrets from log entries before they leave the process.
+
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+
+use crate::{LogEntry, ObservabilityError};
+
+/// Replacement for redacted values.
+pub const REDACTED: &str = "***REDACTED***";
+
+/// Ready-made rules for `RedactionConfig::with_preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionPreset {
+    /// `Bearer <token>` credentials, e.g. from a logged `Authorization` header.
+    BearerTokens,
+    /// Runs of 13-19 digits, optionally separated by spaces or dashes.
+    CardNumbers,
+    /// Fields and labels named `password`, `secret`, `token`, `api_key`, ...
+    SecretFields,
+}
+
+const BEARER_TOKEN_PATTERN: &str = r"(?i)\bbearer\s+[a-z0-9\-._~+/]+=*";
+const CARD_NUMBER_PATTERN: &str = r"\b(?:\d[ -]?){12,18}\d\b";
+const SECRET_FIELDS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "api_key",
+    "apikey",
+    "authorization",
+];
+
+/// What `ObservabilityClient::with_redaction` scrubs from log entries
+///
+/// Text matching a pattern is replaced with `REDACTED` in the message, in every
+/// string of the JSON payload and in label values. JSON fields and labels whose
+/// name is listed (case-insensitive) have their whole value replaced.
+///
+/// ```rust
+/// use gcp_rust_tools::{RedactionConfig, RedactionPreset};
+///
+/// let redaction = RedactionConfig::new()
+///     .with_preset(RedactionPreset::BearerTokens)
+///     .with_field("ssn")
+///     .with_pattern(r"sk_live_[0-9a-zA-Z]+")
+///     .unwrap();
+/// assert_eq!(
+///     redaction.redact_text("auth: Bearer abc.def key=sk_live_123"),
+///     "auth: ***REDACTED*** key=***REDACTED***"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RedactionConfig {
+    patterns: Vec<Regex>,
+    fields: HashSet<String>,
+}
+
+impl RedactionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redact text matching `pattern` (regex syntax).
+    ///
+    /// Fails with `SetupError` if the pattern doesn't compile.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, ObservabilityError> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            ObservabilityError::SetupError(format!(
+                "Invalid redaction pattern '{}': {}",
+                pattern, e
+            ))
+        })?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Redact the whole value of JSON fields and labels named `name`.
+    pub fn with_field(mut self, name: impl Into<String>) -> Self {
+        self.fields.insert(name.into().to_lowercase());
+        self
+    }
+
+    pub fn with_preset(self, preset: RedactionPreset) -> Self {
+        match preset {
+            RedactionPreset::BearerTokens => self.with_builtin_pattern(BEARER_TOKEN_PATTERN),
+            RedactionPreset::CardNumbers => self.with_builtin_pattern(CARD_NUMBER_PATTERN),
+            RedactionPreset::SecretFields => SECRET_FIELDS
+                .iter()
+                .fold(self, |config, field| config.with_field(*field)),
+        }
+    }
+
+    fn with_builtin_pattern(self, pattern: &str) -> Self {
+        self.with_pattern(pattern)
+            .expect("built-in redaction patterns compile")
+    }
+
+    /// `text` with every pattern match replaced by `REDACTED`.
+    pub fn redact_text(&self, text: &str) -> String {
+        self.patterns.iter().fold(text.to_string(), |text, regex| {
+            regex.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+
+    fn is_sensitive_field(&self, name: &str) -> bool {
+        self.fields.contains(&name.to_lowercase())
+    }
+
+    fn redact_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.redact_text(text),
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.redact_json(item))
+            }
+            serde_json::Value::Object(fields) => {
+                for (name, field) in fields.iter_mut() {
+                    if self.is_sensitive_field(name) {
+                        *field = serde_json::Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact_json(field);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_labels(&self, labels: &mut HashMap<String, String>) {
+        for (name, value) in labels.iter_mut() {
+            *value = if self.is_sensitive_field(name) {
+                REDACTED.to_string()
+            } else {
+                self.redact_text(value)
+            };
+        }
+    }
+
+    /// Scrub the message, JSON payload and labels of `entry`.
+    pub(crate) fn redact_entry(&self, entry: &mut LogEntry) {
+        entry.message = self.redact_text(&entry.message);
+        if let Some(payload) = &mut entry.json_payload {
+            self.redact_json(payload);
+        }
+        if let Some(labels) = &mut entry.labels {
+            self.redact_labels(labels);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! One `ObservabilityClient` per project, for multi-tenant services.
+
+use std::collections::HashMap;
+
+use tokio::sync::Mutex;
+
+use crate::{ObservabilityClient, ObservabilityClientBuilder, ObservabilityError};
+
+/// Cache of ready clients keyed by (project, credentials)
+///
+/// Every client is built from the same builder template with its project id
+/// overridden. Authentication only runs for the first client of each credentials
+/// file (or token provider); later clients share its setup and token caches. Each
+/// client keeps its own queue, worker and rate limiter, so limits apply per project.
+///
+/// ```rust,no_run
+/// # async fn run() -> Result<(), gcp_rust_tools::GcpError> {
+/// use gcp_rust_tools::{ClientRegistry, LogEntry};
+///
+/// let registry = ClientRegistry::new();
+/// let client = registry.client_for("project-a").await?;
+/// client.send_log(LogEntry::new("INFO", "tenant a"))?;
+/// registry.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    template: ObservabilityClientBuilder,
+    /// Keyed by (project id, credentials path or `"token_provider"`).
+    clients: Mutex<HashMap<(String, String), ObservabilityClient>>,
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `builder` as the template for every client (its project id is ignored).
+    pub fn from_builder(builder: ObservabilityClientBuilder) -> Self {
+        Self {
+            template: builder,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The client for `project_id` with the current credentials, built on first use.
+    pub async fn client_for(
+        &self,
+        project_id: &str,
+    ) -> Result<ObservabilityClient, ObservabilityError> {
+        let key = (project_id.to_string(), self.template.credentials_key()?);
+
+        // Held across the build so concurrent callers don't set up the same client twice.
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let shared = clients
+            .iter()
+            .find(|((_, creds), _)| *creds == key.1)
+            .map(|(_, client)| client);
+        let client = self
+            .template
+            .clone()
+            .with_project_id(project_id)
+            .build_with_auth(shared)
+            .await?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// Shut down every client, returning the first error after trying them all.
+    pub async fn shutdown(&self) -> Result<(), ObservabilityError> {
+        let clients: Vec<_> = self.clients.lock().await.drain().map(|(_, c)| c).collect();
+        let mut result = Ok(());
+        for client in clients {
+            let shutdown = client.shutdown().await;
+            if result.is_ok() {
+                result = shutdown;
+            }
+        }
+        result
+    }
+}
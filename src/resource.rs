@@ -0,0 +1,322 @@
+//! The monitored resource logs and metrics are attributed to.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::ObservabilityError;
+
+const METADATA_URL: &str = "http://metadata.google.internal/computeMetadata/v1";
+
+/// How long `detect` waits for each metadata server lookup.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Labels Cloud Run resources need before logs or metrics can be attributed to them.
+const CLOUD_RUN_LABELS: &[&str] = &[
+    "service_name",
+    "revision_name",
+    "configuration_name",
+    "location",
+];
+
+/// Labels GKE containers need, besides `project_id`.
+const K8S_CONTAINER_LABELS: &[&str] = &[
+    "cluster_name",
+    "location",
+    "namespace_name",
+    "pod_name",
+    "container_name",
+];
+
+/// Labels Compute Engine instances need, besides `project_id`.
+const GCE_INSTANCE_LABELS: &[&str] = &["instance_id", "zone"];
+
+/// Cloud Monitoring / Logging monitored resource (`resource.type` and `resource.labels`)
+///
+/// The `project_id` label is filled in from the client when missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitoredResource {
+    pub resource_type: String,
+    pub labels: HashMap<String, String>,
+    /// Cloud Run instance, used as `task_id` when writing metrics.
+    instance_id: Option<String>,
+}
+
+impl Default for MonitoredResource {
+    fn default() -> Self {
+        Self::global()
+    }
+}
+
+impl MonitoredResource {
+    pub fn new(resource_type: impl Into<String>, labels: HashMap<String, String>) -> Self {
+        Self {
+            resource_type: resource_type.into(),
+            labels,
+            instance_id: None,
+        }
+    }
+
+    /// The `global` resource (the default outside Cloud Run, GKE and GCE).
+    pub fn global() -> Self {
+        Self::new("global", HashMap::new())
+    }
+
+    /// A Cloud Run revision. `location` is the region, e.g. `us-central1`.
+    pub fn cloud_run_revision(
+        service_name: impl Into<String>,
+        revision_name: impl Into<String>,
+        configuration_name: impl Into<String>,
+        location: impl Into<String>,
+    ) -> Self {
+        let labels = HashMap::from([
+            ("service_name".to_string(), service_name.into()),
+            ("revision_name".to_string(), revision_name.into()),
+            ("configuration_name".to_string(), configuration_name.into()),
+            ("location".to_string(), location.into()),
+        ]);
+        Self::new("cloud_run_revision", labels)
+    }
+
+    /// Container instance id, written as `task_id` for Cloud Run metrics (detected
+    /// from the metadata server on Cloud Run).
+    pub fn with_instance_id(mut self, instance_id: impl Into<String>) -> Self {
+        self.instance_id = Some(instance_id.into());
+        self
+    }
+
+    /// A Compute Engine VM, so its metrics appear under the instance in Monitoring.
+    pub fn gce_instance(instance_id: impl Into<String>, zone: impl Into<String>) -> Self {
+        let labels = HashMap::from([
+            ("instance_id".to_string(), instance_id.into()),
+            ("zone".to_string(), zone.into()),
+        ]);
+        Self::new("gce_instance", labels)
+    }
+
+    /// Add (or replace) a resource label.
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Detect the runtime from its environment and the metadata server.
+    ///
+    /// - Cloud Run (`K_SERVICE` set): `cloud_run_revision` with service, revision,
+    ///   configuration and region labels.
+    /// - GKE (`KUBERNETES_SERVICE_HOST` set): `k8s_container` with cluster, location,
+    ///   namespace, pod and container labels. Namespace and pod come from the
+    ///   `POD_NAMESPACE`/`POD_NAME` env vars (Downward API) when set, otherwise from
+    ///   the service account mount and the hostname; the container from `CONTAINER_NAME`.
+    /// - GCE (the DMI product name is `Google Compute Engine`): `gce_instance` with the
+    ///   instance id and zone.
+    /// - Anywhere else: `global`. The metadata server is only queried on Cloud Run,
+    ///   GKE and GCE, so this doesn't slow down local runs.
+    ///
+    /// Labels the metadata server can't provide are left empty on Cloud Run, so
+    /// `build` fails naming them; an incomplete GKE or GCE resource falls back to
+    /// `global` with a warning.
+    pub async fn detect() -> Self {
+        if let Some(service) = env_var("K_SERVICE") {
+            let metadata = MetadataClient::new();
+            let region = metadata
+                .get("instance/region")
+                .await
+                .map(|r| last_path_segment(&r))
+                .unwrap_or_default();
+            let labels = HashMap::from([
+                ("service_name".to_string(), service),
+                (
+                    "revision_name".to_string(),
+                    env_var("K_REVISION").unwrap_or_default(),
+                ),
+                (
+                    "configuration_name".to_string(),
+                    env_var("K_CONFIGURATION").unwrap_or_default(),
+                ),
+                ("location".to_string(), region),
+            ]);
+            return Self {
+                instance_id: metadata.get("instance/id").await,
+                ..Self::new("cloud_run_revision", labels)
+            };
+        }
+
+        if env_var("KUBERNETES_SERVICE_HOST").is_some() {
+            let metadata = MetadataClient::new();
+            let namespace = match env_var("POD_NAMESPACE") {
+                Some(namespace) => namespace,
+                None => tokio::fs::read_to_string(
+                    "/var/run/secrets/kubernetes.io/serviceaccount/namespace",
+                )
+                .await
+                .map(|n| n.trim().to_string())
+                .unwrap_or_default(),
+            };
+            let labels = HashMap::from([
+                (
+                    "cluster_name".to_string(),
+                    metadata
+                        .get("instance/attributes/cluster-name")
+                        .await
+                        .unwrap_or_default(),
+                ),
+                (
+                    "location".to_string(),
+                    metadata
+                        .get("instance/attributes/cluster-location")
+                        .await
+                        .unwrap_or_default(),
+                ),
+                ("namespace_name".to_string(), namespace),
+                (
+                    "pod_name".to_string(),
+                    env_var("POD_NAME")
+                        .or_else(|| env_var("HOSTNAME"))
+                        .unwrap_or_default(),
+                ),
+                (
+                    "container_name".to_string(),
+                    env_var("CONTAINER_NAME").unwrap_or_default(),
+                ),
+            ]);
+            return Self::new("k8s_container", labels).or_global();
+        }
+
+        if on_gce().await {
+            let metadata = MetadataClient::new();
+            let instance_id = metadata.get("instance/id").await.unwrap_or_default();
+            let zone = metadata
+                .get("instance/zone")
+                .await
+                .map(|z| last_path_segment(&z))
+                .unwrap_or_default();
+            return Self::gce_instance(instance_id, zone).or_global();
+        }
+
+        Self::global()
+    }
+
+    /// `self` if it has its required labels, otherwise `global`.
+    fn or_global(self) -> Self {
+        match self.validate() {
+            Ok(()) => self,
+            Err(e) => {
+                log::warn!("{}; using the global resource instead", e);
+                Self::global()
+            }
+        }
+    }
+
+    /// Check that a `cloud_run_revision`, `k8s_container` or `gce_instance` resource
+    /// has all of its required labels.
+    ///
+    /// Monitoring and Logging reject or misfile data for these resources without
+    /// them (e.g. `location` for Cloud Run, `container_name` for GKE), so the client
+    /// runs this when it is built and before sending. Other resource types aren't
+    /// checked.
+    pub fn validate(&self) -> Result<(), ObservabilityError> {
+        let (required, constructor) = match self.resource_type.as_str() {
+            "cloud_run_revision" => (CLOUD_RUN_LABELS, "MonitoredResource::cloud_run_revision"),
+            "k8s_container" => (K8S_CONTAINER_LABELS, "MonitoredResource::new"),
+            "gce_instance" => (GCE_INSTANCE_LABELS, "MonitoredResource::gce_instance"),
+            _ => return Ok(()),
+        };
+        let missing: Vec<&str> = required
+            .iter()
+            .copied()
+            .filter(|key| self.labels.get(*key).is_none_or(|v| v.trim().is_empty()))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(ObservabilityError::SetupError(format!(
+            "{} resource is missing required labels: {} (set them with {} or with_resource_label)",
+            self.resource_type,
+            missing.join(", "),
+            constructor
+        )))
+    }
+
+    /// `resource` JSON for log entries.
+    pub(crate) fn to_json(&self, project_id: &str) -> serde_json::Value {
+        let mut labels = self.labels.clone();
+        labels
+            .entry("project_id".to_string())
+            .or_insert_with(|| project_id.to_string());
+        json!({ "type": self.resource_type, "labels": labels })
+    }
+
+    /// `resource` JSON for time series.
+    ///
+    /// Custom metrics can't be written against `cloud_run_revision`, so Cloud Run
+    /// metrics use `generic_task` (location, service as namespace, revision as job,
+    /// instance as task), as Google recommends.
+    pub(crate) fn to_metric_json(&self, project_id: &str) -> serde_json::Value {
+        if self.resource_type != "cloud_run_revision" {
+            return self.to_json(project_id);
+        }
+        let label = |key: &str| self.labels.get(key).cloned().unwrap_or_default();
+        let labels = HashMap::from([
+            ("location".to_string(), label("location")),
+            ("namespace".to_string(), label("service_name")),
+            ("job".to_string(), label("revision_name")),
+            (
+                "task_id".to_string(),
+                self.instance_id.clone().unwrap_or_default(),
+            ),
+        ]);
+        Self::new("generic_task", labels).to_json(project_id)
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Whether this is a Compute Engine VM, judged from the DMI product name so no
+/// network call is made elsewhere (Linux only).
+async fn on_gce() -> bool {
+    tokio::fs::read_to_string("/sys/class/dmi/id/product_name")
+        .await
+        .is_ok_and(|name| name.trim().starts_with("Google Compute Engine"))
+}
+
+/// `projects/123/regions/us-central1` -> `us-central1`
+fn last_path_segment(value: &str) -> String {
+    value.rsplit('/').next().unwrap_or(value).to_string()
+}
+
+/// Minimal GCE metadata server reader
+struct MetadataClient {
+    http: Option<reqwest::Client>,
+}
+
+impl MetadataClient {
+    fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(METADATA_TIMEOUT)
+                .build()
+                .ok(),
+        }
+    }
+
+    /// A metadata value, or `None` if the server is unreachable or lacks it.
+    async fn get(&self, path: &str) -> Option<String> {
+        let response = self
+            .http
+            .as_ref()?
+            .get(format!("{}/{}", METADATA_URL, path))
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let value = response.text().await.ok()?;
+        Some(value.trim().to_string())
+    }
+}
@@ -0,0 +1,82 @@
+//! RAII spans that are sent to Cloud Trace when they go out of scope.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{ObservabilityClient, TraceContext, TraceSpan};
+
+/// A span timed from creation until it is dropped
+///
+/// `Drop` can't await, so the finished span is queued on the client's background
+/// worker with a non-blocking send. If the queue is full the span is discarded with
+/// a warning (and counted in `WorkerMetrics`) rather than blocking the destructor,
+/// so guards are safe in sync code and other `Drop` impls.
+///
+/// ```rust,no_run
+/// # fn run(client: &gcp_rust_tools::ObservabilityClient) {
+/// let mut span = client.start_span("load_user");
+/// span.span_mut().attributes.insert("user_id".into(), "42".into());
+/// {
+///     let _db = span.child("db_query");
+/// } // db_query is sent here
+/// # }
+/// ```
+pub struct SpanGuard {
+    client: ObservabilityClient,
+    /// `None` once the span has been cancelled.
+    span: Option<TraceSpan>,
+    started: Instant,
+}
+
+impl SpanGuard {
+    pub(crate) fn new(client: ObservabilityClient, span: TraceSpan) -> Self {
+        Self {
+            client,
+            span: Some(span),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn span(&self) -> &TraceSpan {
+        self.span.as_ref().expect("span is only taken on drop")
+    }
+
+    /// Mutable access, e.g. to set the status or add annotations before the span ends.
+    pub fn span_mut(&mut self) -> &mut TraceSpan {
+        self.span.as_mut().expect("span is only taken on drop")
+    }
+
+    /// Context for propagating this span as the parent of downstream work.
+    pub fn context(&self) -> TraceContext {
+        TraceContext::from_span(self.span())
+    }
+
+    /// Start a child span in the same trace, sent through the same client.
+    pub fn child(&self, display_name: impl Into<String>) -> SpanGuard {
+        let span = self
+            .span()
+            .child(display_name, SystemTime::now(), Duration::ZERO);
+        SpanGuard::new(self.client.clone(), span)
+    }
+
+    /// Discard the span without sending it.
+    pub fn cancel(mut self) {
+        self.span = None;
+    }
+}
+
+impl std::fmt::Debug for SpanGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpanGuard")
+            .field("span", &self.span)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some(mut span) = self.span.take() {
+            span.duration = self.started.elapsed();
+            self.client.try_send_trace(span);
+        }
+    }
+}
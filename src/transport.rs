@@ -0,0 +1,194 @@
+//! The HTTP layer used for Logging, Monitoring and Trace API calls.
+//!
+//! Replace it with `ObservabilityClientBuilder::with_transport`, e.g. with a mock
+//! asserting request bodies or simulating `429`/`500` responses in tests.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// An API request, with authentication and content headers already set
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: reqwest::Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// JSON body, gzipped when the `content-encoding: gzip` header is set.
+    pub body: Option<Vec<u8>>,
+    /// Overrides the transport's own timeout, e.g. from `SendOptions`.
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    /// Value of the first header named `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl std::fmt::Debug for HttpRequest {
+    /// The `authorization` header is redacted so requests can be logged safely, and
+    /// the body is shown as its length.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                if name.eq_ignore_ascii_case("authorization") {
+                    (name.as_str(), "<redacted>")
+                } else {
+                    (name.as_str(), value.as_str())
+                }
+            })
+            .collect();
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body", &self.body.as_ref().map(|body| body.len()))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Status and body of an API response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Why a request got no response
+#[derive(Debug)]
+pub enum TransportError {
+    /// The request did not complete within its timeout.
+    Timeout(String),
+    /// The reqwest client failed (connection, TLS, ...).
+    Http(reqwest::Error),
+    /// Any other failure of a custom transport.
+    Other(String),
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TransportError::Timeout(msg) => write!(f, "timed out: {}", msg),
+            TransportError::Http(e) => write!(f, "HTTP error: {}", e),
+            TransportError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TransportError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            TransportError::Timeout(e.to_string())
+        } else {
+            TransportError::Http(e)
+        }
+    }
+}
+
+/// Sends API requests for an `ObservabilityClient`
+///
+/// Retries, re-authentication and rate limiting happen in the client, around
+/// each `send`, so a transport only has to perform one request.
+///
+/// ```rust
+/// use gcp_rust_tools::{
+///     HttpRequest, HttpResponse, LogEntry, MonitoredResource, ObservabilityClient, Transport,
+///     TransportError,
+/// };
+/// use std::sync::{Arc, Mutex};
+///
+/// /// Records request bodies and answers with canned statuses, then `200`.
+/// #[derive(Clone, Default)]
+/// struct MockTransport {
+///     bodies: Arc<Mutex<Vec<String>>>,
+///     statuses: Arc<Mutex<Vec<u16>>>,
+/// }
+///
+/// #[async_trait::async_trait]
+/// impl Transport for MockTransport {
+///     async fn send(&self, req: HttpRequest) -> Result<HttpResponse, TransportError> {
+///         let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+///         self.bodies.lock().unwrap().push(body.into_owned());
+///         let status = self.statuses.lock().unwrap().pop().unwrap_or(200);
+///         Ok(HttpResponse { status, body: String::new() })
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), gcp_rust_tools::ObservabilityError> {
+/// let transport = MockTransport::default();
+/// transport.statuses.lock().unwrap().push(401); // first call: expired token
+/// let client = ObservabilityClient::builder()
+///     .with_project_id("test-project")
+///     .with_token_provider(|| async { Ok::<_, std::io::Error>("test-token".to_string()) })
+///     .with_resource(MonitoredResource::global())
+///     .with_transport(transport.clone())
+///     .build()
+///     .await?;
+///
+/// client.send_logs(vec![LogEntry::new("INFO", "hello")]).await?;
+/// let bodies = transport.bodies.lock().unwrap();
+/// assert_eq!(bodies.len(), 2); // retried after the 401
+/// assert!(bodies[1].contains("hello"));
+/// # drop(bodies);
+/// # client.shutdown().await
+/// # }
+/// ```
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, req: HttpRequest) -> Result<HttpResponse, TransportError>;
+}
+
+/// The default transport: a shared reqwest client
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, req: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut request = self.client.request(req.method, &req.url);
+        for (name, value) in &req.headers {
+            request = request.header(name, value);
+        }
+        if let Some(timeout) = req.timeout {
+            request = request.timeout(timeout);
+        }
+        if let Some(body) = req.body {
+            request = request.body(body);
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        Ok(HttpResponse { status, body })
+    }
+}
+
+impl std::fmt::Debug for dyn Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transport")
+    }
+}